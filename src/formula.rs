@@ -94,8 +94,74 @@ impl TokenKind {
 
 type Token = Annot<TokenKind>;
 
+// 構文解析した木を外に渡すための表現　TokenKindと1対1に対応する
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FormulaToken {
+    Number(f64),
+    Variable(String),
+    Plus,
+    Minus,
+    Mul,
+    Div,
+    Mod,
+    Equal,
+    LParen,
+    RParen,
+}
+
+// locは数式文字列の中でのトークンの位置(開始, 終了)
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FormulaNode {
+    pub token: FormulaToken,
+    pub loc: (usize, usize),
+}
+
+// 構文解析済みの数式　FormulaCalculatorに戻せば再度parseせずに計算できる
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ParsedFormula {
+    pub formula: String,
+    pub tree: Node<FormulaNode>,
+}
+
+impl FormulaNode {
+    fn from_token(token: &Token) -> Self {
+        let t = match &token.value {
+            TokenKind::Float(f)    => FormulaToken::Number(*f),
+            TokenKind::Variable(v) => FormulaToken::Variable(v.clone()),
+            TokenKind::Plus        => FormulaToken::Plus,
+            TokenKind::Minus       => FormulaToken::Minus,
+            TokenKind::Mul         => FormulaToken::Mul,
+            TokenKind::Div         => FormulaToken::Div,
+            TokenKind::Mod         => FormulaToken::Mod,
+            TokenKind::Equal       => FormulaToken::Equal,
+            TokenKind::LParen      => FormulaToken::LParen,
+            TokenKind::RParen      => FormulaToken::RParen,
+        };
+        FormulaNode { token: t, loc: (token.loc.0, token.loc.1) }
+    }
+
+    fn into_token(self) -> Token {
+        let value = match self.token {
+            FormulaToken::Number(f)    => TokenKind::Float(f),
+            FormulaToken::Variable(v)  => TokenKind::Variable(v),
+            FormulaToken::Plus         => TokenKind::Plus,
+            FormulaToken::Minus        => TokenKind::Minus,
+            FormulaToken::Mul          => TokenKind::Mul,
+            FormulaToken::Div          => TokenKind::Div,
+            FormulaToken::Mod          => TokenKind::Mod,
+            FormulaToken::Equal        => TokenKind::Equal,
+            FormulaToken::LParen       => TokenKind::LParen,
+            FormulaToken::RParen       => TokenKind::RParen,
+        };
+        Token { value, loc: Loc(self.loc.0, self.loc.1) }
+    }
+}
+
 // FormulaCalculatorは、数式文字列から2分木を構築する + 計算を行う
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct FormulaCalculator { 
     tree: Option<Node<Token>>,
     formula_str: String,
@@ -171,13 +237,13 @@ impl FormulaCalculator {
     fn replace_variable(node: Option<&mut Node<Token>>, vars: &VarPool) -> Result<(), FormulaErr> {
         match node {
             Some(n) => {
-                n.foreach_mut(&SearchOrder::PreOrder, &mut |elem: &mut Token| {
+                for elem in n.iter_mut(&SearchOrder::PreOrder) {
                     if let TokenKind::Variable(v) = &elem.value {                        
                         if let Some(f) = vars.get(v) {          // ここでは、定義されている変数を見つけた場合には値に置き換えている。　
                             elem.value = TokenKind::Float(f);  // Noneが来た場合は何もしない　→　calculate関数を実行したときにVariableを見つけたら未定義としてはじく
                         }
                     }
-                });
+                }
             },
             None => return Err(FormulaErr::new(ErrType::EmptyFormula, "enmpty formula is found.", Loc(0, 0))),
         }
@@ -210,6 +276,31 @@ impl FormulaCalculator {
         }
     }
 
+    // 構文解析した木をGraphvizのdot形式で出力する　parse()する前はNone
+    pub fn to_dot(&self) -> Option<String> {
+        self.tree.as_ref().map(|t| t.to_dot_with(|token| DotStyle::new(&token.value.to_string())))
+    }
+
+    // 構文解析した木を罫線文字で描く　parse()する前はNone
+    pub fn render_text(&self, style: &TextStyle) -> Option<String> {
+        self.tree.as_ref().map(|t| t.render_text_with(style, |token| token.value.to_string()))
+    }
+
+    // 構文解析した木を公開用の表現で取り出す　parse()する前はNone
+    pub fn to_parsed(&self) -> Option<ParsedFormula> {
+        self.tree.as_ref().map(|t| ParsedFormula {
+            formula: self.formula_str.clone(),
+            tree: t.map_ref(FormulaNode::from_token),
+        })
+    }
+
+    pub fn from_parsed(parsed: ParsedFormula) -> Self {
+        FormulaCalculator {
+            tree: Some(parsed.tree.map(FormulaNode::into_token)),
+            formula_str: parsed.formula,
+        }
+    }
+
     pub fn parse(&mut self, formula: &str) -> Result<(), FormulaErr> {
         self.formula_str = formula.to_string();
 
@@ -565,6 +656,111 @@ mod tests {
 
     }
 
+    #[test]
+    fn to_dot_test() {
+        let mut fc = FormulaCalculator::new();
+        assert_eq!(fc.to_dot(), None);
+
+        fc.parse("1 + 2 * x").unwrap();
+        let dot = fc.to_dot().unwrap();
+
+        assert!(dot.starts_with("digraph tree {\n    n0 [label=\"+\"];\n"));
+        assert!(dot.contains("n2 [label=\"*\"];"));
+        assert!(dot.contains("n2 -> n4 [label=\"R\"];"));
+        assert!(dot.contains("n4 [label=\"x\"];"));
+    }
+
+    #[test]
+    fn render_text_test() {
+        let mut fc = FormulaCalculator::new();
+        assert_eq!(fc.render_text(&TextStyle::default()), None);
+
+        fc.parse("x = 1 + 2 * 3").unwrap();
+        let expected = "=\n├── x\n└── +\n    ├── 1\n    └── *\n        ├── 2\n        └── 3\n";
+        assert_eq!(fc.render_text(&TextStyle::default()).unwrap(), expected);
+    }
+
+    #[test]
+    fn parsed_formula_test() {
+        let fc = FormulaCalculator::new();
+        assert!(fc.to_parsed().is_none());
+
+        let fc = FormulaCalculator::set_formula("1 + x * 3").unwrap();
+        let parsed = fc.to_parsed().unwrap();
+        assert_eq!(parsed.formula, "1 + x * 3");
+        assert_eq!(parsed.tree.as_ref(), &FormulaNode { token: FormulaToken::Plus, loc: (2, 3) });
+        assert_eq!(parsed.tree.right().unwrap().left().unwrap().as_ref().token, FormulaToken::Variable("x".to_string()));
+
+        let mut pool = VarPool::new();
+        pool.insert(VarData("x".to_string(), 2.0));
+        let mut back = FormulaCalculator::from_parsed(parsed);
+        assert_eq!(back.calc(&pool).unwrap().1, 7.0);
+    }
+
+    #[test]
+    fn visitor_test() {
+        // 変数を値に置き換える
+        struct Replace(HashMap<String, f64>);
+
+        impl VisitorMut<FormulaNode> for Replace {
+            fn visit_leaf(&mut self, data: &mut FormulaNode, _depth: usize) -> VisitControl {
+                if let FormulaToken::Variable(name) = &data.token {
+                    if let Some(v) = self.0.get(name) {
+                        data.token = FormulaToken::Number(*v);
+                    }
+                }
+                VisitControl::Continue
+            }
+        }
+
+        // 最初に見つかった未定義の変数
+        struct FindVariable(Option<String>);
+
+        impl Visitor<FormulaNode> for FindVariable {
+            fn visit_leaf(&mut self, data: &FormulaNode, _depth: usize) -> VisitControl {
+                match &data.token {
+                    FormulaToken::Variable(name) => {
+                        self.0 = Some(name.clone());
+                        VisitControl::Stop
+                    }
+                    _ => VisitControl::Continue,
+                }
+            }
+        }
+
+        let fc = FormulaCalculator::set_formula("x * (y + 1) - x").unwrap();
+        let mut parsed = fc.to_parsed().unwrap();
+
+        let mut find = FindVariable(None);
+        assert!(!walk(&parsed.tree, &mut find));
+        assert_eq!(find.0, Some("x".to_string()));
+
+        let vars = vec![("x".to_string(), 3.0)].into_iter().collect();
+        assert!(walk_mut(&mut parsed.tree, &mut Replace(vars)));
+
+        let mut find = FindVariable(None);
+        assert!(!walk(&parsed.tree, &mut find));
+        assert_eq!(find.0, Some("y".to_string()));
+
+        let mut pool = VarPool::new();
+        pool.insert(VarData("y".to_string(), 4.0));
+        let mut back = FormulaCalculator::from_parsed(parsed);
+        assert_eq!(back.calc(&pool).unwrap().1, 12.0);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_test() {
+        let fc = FormulaCalculator::set_formula("y = (1 + 2) * 3").unwrap();
+
+        let json = serde_json::to_string(&fc.to_parsed().unwrap()).unwrap();
+        let parsed: ParsedFormula = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.formula, "y = (1 + 2) * 3");
+
+        let mut back = FormulaCalculator::from_parsed(parsed);
+        assert_eq!(back.calc(&VarPool::new()).unwrap(), VarData("y".to_string(), 9.0));
+    }
+
     #[test]
     fn test_lexnumber() {
         let mut pos = 0;
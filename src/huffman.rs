@@ -0,0 +1,335 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use crate::tree::{Node, NodePath, Side};
+
+// ハフマン符号
+// 木は葉に記号(Some)、内部ノードにNoneを持つ　左が0、右が1
+// 頻度から作った木の符号長をもとに正準ハフマン符号の木を作り直すので、
+// 符号表は(記号, 符号長)の一覧だけで保存・復元できる
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum HuffmanError {
+    Empty,              // 頻度がすべて0
+    UnknownSymbol(u8),  // 符号表にない記号を符号化しようとした
+    InvalidCode(usize), // このビット位置から始まる符号が符号表にない
+    InvalidTable,       // 符号表が壊れている
+    Truncated,          // データが途中で切れている
+}
+
+#[derive(Debug, Clone)]
+pub struct HuffmanCode {
+    tree: Node<Option<u8>>,
+    codes: Vec<Option<NodePath>>,   // 記号ごとの符号 = 根から葉までの経路
+}
+
+impl HuffmanCode {
+    pub fn from_frequencies(freq: &[u64; 256]) -> Result<Self, HuffmanError> {
+        // (重み, 作った順) の小さい順に2つずつまとめる　同じ重みなら先に作った方から
+        let mut nodes: Vec<Option<Node<Option<u8>>>> = Vec::new();
+        let mut heap = BinaryHeap::new();
+
+        for (sym, &f) in freq.iter().enumerate() {
+            if f > 0 {
+                heap.push(Reverse((f, nodes.len())));
+                nodes.push(Some(Node::new(Some(sym as u8))));
+            }
+        }
+
+        if heap.is_empty() {
+            return Err( HuffmanError::Empty );
+        }
+
+        while heap.len() > 1 {
+            let Reverse((wa, a)) = heap.pop().unwrap();
+            let Reverse((wb, b)) = heap.pop().unwrap();
+
+            let mut parent = Node::new(None);
+            parent.replace_left(nodes[a].take().unwrap());
+            parent.replace_right(nodes[b].take().unwrap());
+
+            heap.push(Reverse((wa + wb, nodes.len())));
+            nodes.push(Some(parent));
+        }
+
+        let Reverse((_, root)) = heap.pop().unwrap();
+        let tree = nodes[root].take().unwrap();
+
+        // 記号が1種類だけのときも、符号長は1にする
+        let lengths: Vec<(u8, usize)> = tree.iter_with_path()
+            .filter_map(|(path, sym)| sym.map(|s| (s, path.len().max(1))))
+            .collect();
+
+        Self::from_lengths(&lengths)
+    }
+
+    pub fn from_data(data: &[u8]) -> Result<Self, HuffmanError> {
+        let mut freq = [0u64; 256];
+        for &b in data {
+            freq[b as usize] += 1;
+        }
+        Self::from_frequencies(&freq)
+    }
+
+    // (記号, 符号長)の一覧から正準ハフマン符号を作る
+    fn from_lengths(lengths: &[(u8, usize)]) -> Result<Self, HuffmanError> {
+        let mut sorted = lengths.to_vec();
+        sorted.sort_by_key(|&(sym, len)| (len, sym));
+
+        let mut tree = Node::new(None);
+        let mut code: Vec<Side> = Vec::new();
+        let mut seen = [false; 256];
+
+        for (i, &(sym, len)) in sorted.iter().enumerate() {
+            if len == 0 || seen[sym as usize] {
+                return Err( HuffmanError::InvalidTable );
+            }
+            seen[sym as usize] = true;
+            // 1つ前の符号に1を足し、符号長まで0を付け足す
+            if i > 0 && !increment(&mut code) {
+                return Err( HuffmanError::InvalidTable );
+            }
+            code.resize(len, Side::Left);
+
+            insert_leaf(&mut tree, &code, sym)?;
+        }
+
+        let mut codes = vec![None; 256];
+        for (path, sym) in tree.iter_with_path() {
+            if let Some(s) = sym {
+                codes[*s as usize] = Some(path);
+            }
+        }
+
+        Ok(HuffmanCode { tree, codes })
+    }
+
+    pub fn tree(&self) -> &Node<Option<u8>> {
+        &self.tree
+    }
+
+    pub fn code(&self, sym: u8) -> Option<&NodePath> {
+        self.codes[sym as usize].as_ref()
+    }
+
+    // 符号表 = [記号の数(u16, ビッグエンディアン)] [記号, 符号長]...
+    pub fn table_bytes(&self) -> Vec<u8> {
+        let entries: Vec<(u8, usize)> = self.codes.iter().enumerate()
+            .filter_map(|(sym, code)| code.as_ref().map(|c| (sym as u8, c.len())))
+            .collect();
+
+        let mut out = (entries.len() as u16).to_be_bytes().to_vec();
+        for (sym, len) in entries {
+            out.push(sym);
+            out.push(len as u8);
+        }
+        out
+    }
+
+    pub fn from_table_bytes(bytes: &[u8]) -> Result<Self, HuffmanError> {
+        Self::read_table(bytes).map(|(code, _)| code)
+    }
+
+    // 符号表を読んで、読んだバイト数と一緒に返す
+    fn read_table(bytes: &[u8]) -> Result<(Self, usize), HuffmanError> {
+        if bytes.len() < 2 {
+            return Err( HuffmanError::Truncated );
+        }
+        let count = u16::from_be_bytes([bytes[0], bytes[1]]) as usize;
+        let end = 2 + count * 2;
+        if count == 0 || count > 256 {
+            return Err( HuffmanError::InvalidTable );
+        }
+        if bytes.len() < end {
+            return Err( HuffmanError::Truncated );
+        }
+
+        let lengths: Vec<(u8, usize)> = bytes[2..end].chunks(2).map(|c| (c[0], c[1] as usize)).collect();
+        Ok((Self::from_lengths(&lengths)?, end))
+    }
+
+    // 符号化したバイト列と、その中の有効なビット数を返す　ビットは各バイトの上位から詰める
+    pub fn encode(&self, data: &[u8]) -> Result<(Vec<u8>, usize), HuffmanError> {
+        let mut out = Vec::new();
+        let mut bits = 0;
+
+        for &b in data {
+            let code = self.code(b).ok_or(HuffmanError::UnknownSymbol(b))?;
+            for side in code.sides() {
+                if bits % 8 == 0 {
+                    out.push(0);
+                }
+                if *side == Side::Right {
+                    *out.last_mut().unwrap() |= 0x80 >> (bits % 8);
+                }
+                bits += 1;
+            }
+        }
+
+        Ok((out, bits))
+    }
+
+    pub fn decode(&self, bytes: &[u8], bits: usize) -> Result<Vec<u8>, HuffmanError> {
+        if bits > bytes.len() * 8 {
+            return Err( HuffmanError::Truncated );
+        }
+
+        let mut out = Vec::new();
+        let mut node = &self.tree;
+        let mut start = 0;
+
+        for i in 0..bits {
+            let bit = bytes[i / 8] & (0x80 >> (i % 8)) != 0;
+            let next = if bit { node.right() } else { node.left() };
+            node = next.ok_or(HuffmanError::InvalidCode(start))?;
+
+            if let Some(sym) = node.as_ref() {
+                out.push(*sym);
+                node = &self.tree;
+                start = i + 1;
+            }
+        }
+
+        if start != bits {
+            return Err( HuffmanError::Truncated );
+        }
+        Ok(out)
+    }
+
+    // 符号表, ビット数(u64, ビッグエンディアン), 符号化したデータ をまとめたバイト列にする
+    pub fn compress(data: &[u8]) -> Result<Vec<u8>, HuffmanError> {
+        let code = Self::from_data(data)?;
+        let (payload, bits) = code.encode(data)?;
+
+        let mut out = code.table_bytes();
+        out.extend_from_slice(&(bits as u64).to_be_bytes());
+        out.extend(payload);
+        Ok(out)
+    }
+
+    pub fn decompress(bytes: &[u8]) -> Result<Vec<u8>, HuffmanError> {
+        let (code, pos) = Self::read_table(bytes)?;
+        if bytes.len() < pos + 8 {
+            return Err( HuffmanError::Truncated );
+        }
+
+        let mut len = [0u8; 8];
+        len.copy_from_slice(&bytes[pos..pos + 8]);
+        code.decode(&bytes[pos + 8..], u64::from_be_bytes(len) as usize)
+    }
+}
+
+// 2進数として1を足す　桁あふれしたらfalse
+fn increment(code: &mut Vec<Side>) -> bool {
+    while let Some(side) = code.pop() {
+        if side == Side::Left {
+            code.push(Side::Right);
+            return true;
+        }
+    }
+    false
+}
+
+fn insert_leaf(tree: &mut Node<Option<u8>>, code: &[Side], sym: u8) -> Result<(), HuffmanError> {
+    let (last, rest) = code.split_last().unwrap();
+
+    let mut node = tree;
+    for side in rest {
+        if node.as_ref().is_some() {
+            return Err( HuffmanError::InvalidTable );
+        }
+        node = match side {
+            Side::Left => if node.left().is_some() { node.left_mut().unwrap() } else { node.create_left_node(None).unwrap() },
+            Side::Right => if node.right().is_some() { node.right_mut().unwrap() } else { node.create_right_node(None).unwrap() },
+        };
+    }
+
+    let added = match last {
+        Side::Left => node.create_left_node(Some(sym)),
+        Side::Right => node.create_right_node(Some(sym)),
+    };
+    added.map(|_| ()).map_err(|_| HuffmanError::InvalidTable)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn code_lengths() {
+        // 頻度 a:45 b:13 c:12 d:16 e:9 f:5 (よくある例)
+        let mut freq = [0u64; 256];
+        for (c, f) in [(b'a', 45), (b'b', 13), (b'c', 12), (b'd', 16), (b'e', 9), (b'f', 5)].iter() {
+            freq[*c as usize] = *f;
+        }
+
+        let code = HuffmanCode::from_frequencies(&freq).unwrap();
+        let len = |c: u8| code.code(c).unwrap().len();
+        assert_eq!(len(b'a'), 1);
+        assert_eq!(len(b'b'), 3);
+        assert_eq!(len(b'c'), 3);
+        assert_eq!(len(b'd'), 3);
+        assert_eq!(len(b'e'), 4);
+        assert_eq!(len(b'f'), 4);
+        assert!(code.code(b'g').is_none());
+
+        // 正準符号なので、短い符号・同じ長さなら小さい記号から順に割り当てられる
+        assert_eq!(code.code(b'a').unwrap().to_string(), "/L");
+        assert_eq!(code.code(b'b').unwrap().to_string(), "/R/L/L");
+        assert_eq!(code.code(b'f').unwrap().to_string(), "/R/R/R/R");
+        assert!(code.tree().is_full());
+    }
+
+    #[test]
+    fn encode_decode() {
+        let data = b"1 + 2 * (3 + 15 / (1 + 3)) + 1 / 2";
+        let code = HuffmanCode::from_data(data).unwrap();
+
+        let (bytes, bits) = code.encode(data).unwrap();
+        assert!(bytes.len() < data.len());
+        assert_eq!(code.decode(&bytes, bits).unwrap(), data.to_vec());
+
+        assert_eq!(code.encode(b"x").unwrap_err(), HuffmanError::UnknownSymbol(b'x'));
+        assert_eq!(code.decode(&bytes, bits - 1).unwrap_err(), HuffmanError::Truncated);
+        assert_eq!(code.decode(&bytes, bytes.len() * 8 + 1).unwrap_err(), HuffmanError::Truncated);
+    }
+
+    #[test]
+    fn single_symbol() {
+        let code = HuffmanCode::from_data(b"aaaa").unwrap();
+        assert_eq!(code.code(b'a').unwrap().len(), 1);
+
+        let (bytes, bits) = code.encode(b"aaaa").unwrap();
+        assert_eq!(bits, 4);
+        assert_eq!(code.decode(&bytes, bits).unwrap(), b"aaaa".to_vec());
+        assert_eq!(code.decode(&[0xff], 1).unwrap_err(), HuffmanError::InvalidCode(0));
+
+        assert_eq!(HuffmanCode::from_data(b"").unwrap_err(), HuffmanError::Empty);
+    }
+
+    #[test]
+    fn table_round_trip() {
+        let data: Vec<u8> = (0..=255u8).chain(b"hello, world".iter().copied()).collect();
+        let code = HuffmanCode::from_data(&data).unwrap();
+
+        let table = code.table_bytes();
+        let restored = HuffmanCode::from_table_bytes(&table).unwrap();
+        for sym in 0..=255u8 {
+            assert_eq!(code.code(sym), restored.code(sym));
+        }
+
+        assert_eq!(HuffmanCode::from_table_bytes(&table[..5]).unwrap_err(), HuffmanError::Truncated);
+        // 長さ1の符号が3つはありえない
+        assert_eq!(HuffmanCode::from_table_bytes(&[0, 3, b'a', 1, b'b', 1, b'c', 1]).unwrap_err(), HuffmanError::InvalidTable);
+        assert_eq!(HuffmanCode::from_table_bytes(&[0, 2, b'a', 1, b'a', 2]).unwrap_err(), HuffmanError::InvalidTable);
+    }
+
+    #[test]
+    fn compress() {
+        let data = b"x = (1 + 2) * 3; y = (x + 2) * 3; z = x * y".repeat(20);
+        let packed = HuffmanCode::compress(&data).unwrap();
+        assert!(packed.len() < data.len());
+        assert_eq!(HuffmanCode::decompress(&packed).unwrap(), data);
+        assert_eq!(HuffmanCode::decompress(&packed[..packed.len() - 1]).unwrap_err(), HuffmanError::Truncated);
+    }
+}
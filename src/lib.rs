@@ -1,6 +1,7 @@
 pub mod tree;
 pub mod formula;
 pub mod varpool;
+pub mod huffman;
 
 #[cfg(test)]
 mod tests {
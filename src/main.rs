@@ -1,6 +1,7 @@
 use std::io::{stdin, BufRead, BufReader};
 
 use bintree::formula::{*};
+use bintree::tree::{TextLayout, TextStyle};
 use bintree::varpool::{*};
 
 fn main() {
@@ -9,14 +10,41 @@ fn main() {
     let reader = stdin.lock();
 
     let mut varpool = VarPool::new();
+    let mut last_tree: Option<FormulaCalculator> = None;  // :tree で表示する直前の数式
 
     for line in reader.lines() {
         let line = line.unwrap();
-        
-        match line.eval(&varpool) {
-            Ok(dat) => {
-                println!("=> {}", dat);
-                varpool.insert(dat);
+
+        // :tree [top] [ascii] で直前の数式の構文木を表示する
+        let mut words = line.split_whitespace();
+        if words.next() == Some(":tree") {
+            let mut style = TextStyle::default();
+            for w in words {
+                match w {
+                    "top" => style.layout = TextLayout::TopDown,
+                    "ascii" => style.ascii = true,
+                    _ => (),
+                }
+            }
+
+            match last_tree.as_ref().and_then(|f| f.render_text(&style)) {
+                Some(text) => print!("{}", text),
+                None => println!("no formula yet."),
+            }
+            continue;
+        }
+
+        match line.to_formula() {
+            Ok(mut f) => {
+                last_tree = Some(f.clone());   // calc()は変数を値に置き換えてしまうので、計算する前の木を残しておく
+
+                match f.calc(&varpool) {
+                    Ok(dat) => {
+                        println!("=> {}", dat);
+                        varpool.insert(dat);
+                    },
+                    Err( e ) => e.print(),
+                }
             },
             Err( e ) => e.print(),
         }
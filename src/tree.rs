@@ -1,48 +1,146 @@
 
-#[derive(Debug)]
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::FusedIterator;
+use std::ops::{Deref, DerefMut};
+
+mod ordered;
+mod bst;
+mod avl;
+mod rbtree;
+mod cursor;
+mod arena;
+mod dot;
+mod render;
+mod combinator;
+mod path;
+mod persistent;
+mod rebuild;
+mod sexpr;
+mod array;
+mod heap;
+mod diff;
+mod interval;
+mod segment;
+mod splay;
+mod treap;
+mod visitor;
+#[cfg(feature = "serde")]
+mod serde_impl;
+#[cfg(test)]
+pub(crate) mod test_util;
+
+pub use self::ordered::{Entry, MapIter, MapRange, InvariantError, OrderedMap};
+pub use self::bst::BstMap;
+pub use self::avl::AvlTree;
+pub use self::rbtree::{RbTree, Color};
+pub use self::cursor::Cursor;
+pub use self::arena::{ArenaTree, NodeId};
+pub use self::dot::DotStyle;
+pub use self::render::{TextLayout, TextStyle};
+pub use self::path::{NodePath, PathIter};
+pub use self::persistent::{PersistentTree, PersistentIter};
+pub use self::rebuild::RebuildError;
+pub use self::sexpr::{SexprError, SexprErrorKind};
+pub use self::heap::{TreeHeap, HeapRepr};
+pub use self::diff::TreeChange;
+pub use self::interval::{IntervalTree, IntervalMeta};
+pub use self::segment::{SegmentTree, Segment};
+pub use self::splay::SplayTree;
+pub use self::treap::Treap;
+pub use self::visitor::{Visitor, VisitorMut, VisitControl, walk, walk_mut};
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SearchOrder {
     PreOrder,   // 行きがけ順　ノード→左→右
     InOrder,    // 通りがけ順　左→ノード→右
     PostOrder,  // 帰りがけ順　左→右→ノード
+    LevelOrder, // レベル順　　根に近い段から順に、各段は左→右
 }
 
 #[derive(Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum NodeError {
     ChildAddError,  // 子要素が既にあるのに子要素に追加しようとしたとき
+    RotateError,    // 回転に必要な子要素がないとき
+    NoNode,         // 移動先や操作の対象になるノードがないとき
+    AttachError,    // 既に親があるノードや、自分の祖先を子として付けようとしたとき
+    ShapeError,     // 2つの木の形が違うとき
+}
+
+// 親から見た子の位置
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Side {
+    Left,
+    Right,
 }
 
 #[derive(Debug)]
 pub struct Node<T> {
     data: T,
-    left: Option<Box<Node<T>>>,
-    right: Option<Box<Node<T>>>
+    left: Child<T>,
+    right: Child<T>
+}
+
+// 子ノードへのリンク　Option<Box<Node<T>>>としてそのまま使える
+// 解放をNodeではなくこちらで行うので、Nodeからはdataをムーブで取り出せる
+struct Child<T>(Option<Box<Node<T>>>);
+
+impl<T> Deref for Child<T> {
+    type Target = Option<Box<Node<T>>>;
+
+    fn deref(&self) -> &Option<Box<Node<T>>> {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Child<T> {
+    fn deref_mut(&mut self) -> &mut Option<Box<Node<T>>> {
+        &mut self.0
+    }
+}
+
+impl<T> From<Option<Box<Node<T>>>> for Child<T> {
+    fn from(link: Option<Box<Node<T>>>) -> Self {
+        Child(link)
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Child<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
 }
 
 impl<T> Node<T> {
     pub fn new(data: T) -> Self {
         Node {
             data: data,
-            left: None,
-            right: None,
+            left: Child(None),
+            right: Child(None),
         }
     }
 
     pub fn left(&self) -> Option<&Node<T>> {
-        match &self.left {
+        match &*self.left {
             Some(t) => Some(t.as_ref()),
             None => None
         }
     }
 
     pub fn right(&self) -> Option<&Node<T>> {
-        match &self.right {
+        match &*self.right {
             Some(t) => Some(t.as_ref()),
             None => None
         }
     }
 
     pub fn left_mut(&mut self) -> Option<&mut Node<T>> {
-        match &mut self.left {
+        match &mut *self.left {
             Some(t) => Some(t.as_mut()),
             None => None
         }
@@ -50,7 +148,7 @@ impl<T> Node<T> {
 
     
     pub fn right_mut(&mut self) -> Option<&mut Node<T>> {
-        match &mut self.right {
+        match &mut *self.right {
             Some(t) => Some(t.as_mut()),
             None => None
         }
@@ -71,6 +169,11 @@ impl<T> Node<T> {
         child
     }
     */
+    // 子はChildのdropで解放される
+    fn into_data(self) -> T {
+        self.data
+    }
+
     pub fn take_left(&mut self) -> Option<Node<T>> {
         match self.left.take() {
             Some(t) => Some(*t),
@@ -93,10 +196,48 @@ impl<T> Node<T> {
         self.right.replace(Box::new(tree));
     }
 
+    // 左回転　右の子が新しい根になり、元の根はその左の子になる
+    //     x               y
+    //    / \             / \
+    //   a   y    =>     x   c
+    //      / \         / \
+    //     b   c       a   b
+    pub fn rotate_left(&mut self) -> Result<(), NodeError> {
+        let mut pivot = match self.right.take() {
+            Some(n) => n,
+            None => return Err( NodeError::RotateError ),
+        };
+
+        *self.right = pivot.left.take();
+        std::mem::swap(self, &mut pivot);   // selfがy、pivotがxになる
+        *self.left = Some(pivot);
+
+        Ok(())
+    }
+
+    // 右回転　左の子が新しい根になり、元の根はその右の子になる
+    //       x           y
+    //      / \         / \
+    //     y   c  =>   a   x
+    //    / \             / \
+    //   a   b           b   c
+    pub fn rotate_right(&mut self) -> Result<(), NodeError> {
+        let mut pivot = match self.left.take() {
+            Some(n) => n,
+            None => return Err( NodeError::RotateError ),
+        };
+
+        *self.left = pivot.right.take();
+        std::mem::swap(self, &mut pivot);
+        *self.right = Some(pivot);
+
+        Ok(())
+    }
+
     pub fn add_node_left(&mut self, tree: Node<T>) -> Result<&mut Node<T>, NodeError> {
-        match self.left {
+        match *self.left {
             None => {
-                self.left = Some(Box::new(tree));
+                *self.left = Some(Box::new(tree));
                 Ok( self.left_mut().unwrap() )
             },
             Some(_) => {
@@ -106,9 +247,9 @@ impl<T> Node<T> {
     }
 
     pub fn add_node_right(&mut self, tree: Node<T>) -> Result<&mut Node<T>, NodeError> {
-        match self.right {
+        match *self.right {
             None => {
-                self.right = Some(Box::new(tree));
+                *self.right = Some(Box::new(tree));
                 Ok( self.right_mut().unwrap() )
             },
             Some(_) => {
@@ -118,10 +259,10 @@ impl<T> Node<T> {
     }
 
     pub fn create_left_node(&mut self, value: T) -> Result<&mut Node<T>, NodeError> {
-        match self.left {
+        match *self.left {
             None => {
                 let node = Node::new(value);
-                self.left = Some(Box::new(node));
+                *self.left = Some(Box::new(node));
                 Ok( self.left_mut().unwrap() )
             },
             Some(_) => {
@@ -131,10 +272,10 @@ impl<T> Node<T> {
     }
 
     pub fn create_right_node(&mut self, value: T) -> Result<&mut Node<T>, NodeError> {
-        match self.right {
+        match *self.right {
             None => {
                 let node = Node::new(value);
-                self.right = Some(Box::new(node));
+                *self.right = Some(Box::new(node));
                 Ok( self.right_mut().unwrap() )
             },
             Some(_) => {
@@ -143,100 +284,242 @@ impl<T> Node<T> {
         }
     }
 
+    // 再帰を使わずにイテレータでたどるので、深い木でもスタックが溢れない
     pub fn foreach<'r, F> (&'r self, order: &SearchOrder, func: &mut F) 
     where F: FnMut(&'r T) {
-        match order {
-            SearchOrder::PreOrder => {
-                func(self.as_ref());
-                if self.left.is_some() {
-                    self.left().unwrap().foreach(order, func);
-                }
-                if self.right.is_some() {
-                    self.right().unwrap().foreach(order, func);
-                }
-            },
-            SearchOrder::InOrder => {
-                if self.left.is_some() {
-                    self.left().unwrap().foreach(order, func);
-                }
-                func(self.as_ref());
-                if self.right.is_some() {
-                    self.right().unwrap().foreach(order, func);
-                }
-            },
-            SearchOrder::PostOrder => {
-                if self.left.is_some() {
-                    self.left().unwrap().foreach(order, func);
-                }
-                if self.right.is_some() {
-                    self.right().unwrap().foreach(order, func);
-                }
-                func(self.as_ref());
-            }
+        for x in self.iter(order) {
+            func(x);
         }
     }
 
-    pub fn foreach_mut<'r, F> (&'r mut self, order: &SearchOrder, func: &mut F) 
+    pub fn foreach_mut<F> (&mut self, order: &SearchOrder, func: &mut F) 
     where F: FnMut(&mut T) {
-        match order {
-            SearchOrder::PreOrder => {
-                func(self.as_mut());
-                if self.left_mut().is_some() {
-                    self.left_mut().unwrap().foreach_mut(order, func);
-                }
-                if self.right_mut().is_some() {
-                    self.right_mut().unwrap().foreach_mut(order, func);
-                }
-            },
-            SearchOrder::InOrder => {
-                if self.left_mut().is_some() {
-                    self.left_mut().unwrap().foreach_mut(order, func);
-                }
-                func(self.as_mut());
-                if self.right_mut().is_some() {
-                    self.right_mut().unwrap().foreach_mut(order, func);
+        for x in self.iter_mut(order) {
+            func(x);
+        }
+    }
+
+    pub fn iter(&self, order: &SearchOrder) -> NodeIter<'_, T> {
+        NodeIter {
+            root: self,
+            order: *order,
+            front: Walker::new(self, *order, false),
+            back: None,
+            total: Cell::new(None),
+            yielded: 0,
+        }
+    }
+
+    pub fn iter_mut(&mut self, order: &SearchOrder) -> NodeIterMut<'_, T> {
+        NodeIterMut {
+            stack: vec![StepMut::Node(self)].into(),
+            order: *order,
+        }
+    }
+
+    // (深さ, 要素) をレベル順に返す　根の深さは0
+    pub fn iter_with_depth(&self) -> DepthIter<'_, T> {
+        let mut queue = VecDeque::new();
+        queue.push_back((0, self));
+
+        DepthIter {
+            queue,
+        }
+    }
+
+    // 深さごとに要素をまとめる　levels()[d] が深さdの要素（左→右）
+    pub fn levels(&self) -> Vec<Vec<&T>> {
+        let mut ret: Vec<Vec<&T>> = Vec::new();
+
+        for (depth, x) in self.iter_with_depth() {
+            if ret.len() <= depth {
+                ret.push(Vec::new());
+            }
+            ret[depth].push(x);
+        }
+        ret
+    }
+
+    // 根から最も深い葉までのノード数　根だけなら1
+    pub fn height(&self) -> usize {
+        self.fold(|_, l: Option<usize>, r: Option<usize>| 1 + l.unwrap_or(0).max(r.unwrap_or(0)))
+    }
+
+    // ノードの数　Nodeは必ず根を持つので空にはならない
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        self.iter(&SearchOrder::PreOrder).count()
+    }
+
+    pub fn is_leaf(&self) -> bool {
+        self.left.is_none() && self.right.is_none()
+    }
+
+    pub fn leaf_count(&self) -> usize {
+        self.fold(|_, l: Option<usize>, r: Option<usize>| {
+            match (l, r) {
+                (None, None) => 1,
+                _ => l.unwrap_or(0) + r.unwrap_or(0),
+            }
+        })
+    }
+
+    // 全二分木　すべてのノードの子が0個か2個
+    pub fn is_full(&self) -> bool {
+        self.fold(|_, l: Option<bool>, r: Option<bool>| {
+            match (l, r) {
+                (None, None) => true,
+                (Some(l), Some(r)) => l && r,
+                _ => false,
+            }
+        })
+    }
+
+    // 完全二分木　最後の段以外は埋まっていて、最後の段は左から詰まっている
+    pub fn is_complete(&self) -> bool {
+        let mut queue = VecDeque::new();
+        queue.push_back(self);
+        let mut gap = false;    // レベル順に見て、一度でも子が欠けていたらtrue
+
+        while let Some(node) = queue.pop_front() {
+            for child in &[node.left(), node.right()] {
+                match child {
+                    Some(c) => {
+                        if gap {
+                            return false;
+                        }
+                        queue.push_back(c);
+                    },
+                    None => gap = true,
                 }
-            },
-            SearchOrder::PostOrder => {
-                if self.left_mut().is_some() {
-                    self.left_mut().unwrap().foreach_mut(order, func);
+            }
+        }
+        true
+    }
+
+    // すべてのノードで、左右の部分木の高さの差が1以下
+    pub fn is_balanced(&self) -> bool {
+        let ret = self.fold(|_, l: Option<Option<usize>>, r: Option<Option<usize>>| {
+            // 各部分木の高さ　崩れていればNone
+            let l = l.unwrap_or(Some(0))?;
+            let r = r.unwrap_or(Some(0))?;
+            if l.abs_diff(r) > 1 { None } else { Some(1 + l.max(r)) }
+        });
+        ret.is_some()
+    }
+
+    // 条件に合う最初のノード(行きがけ順)までの経路を返す　根ならば空の経路
+    // iter_with_pathと同じ巡回で、経路を複製するのは見つかったときだけなのでO(n)で済む
+    pub fn find<P>(&self, mut pred: P) -> Option<NodePath>
+    where P: FnMut(&T) -> bool {
+        let mut iter = self.iter_with_path();
+        while let Some((path, x)) = iter.advance() {
+            if pred(x) {
+                return Some(path.iter().copied().collect());
+            }
+        }
+        None
+    }
+
+    // 条件に合う最初のノードの深さ　根の深さは0
+    pub fn depth_of<P>(&self, pred: P) -> Option<usize>
+    where P: FnMut(&T) -> bool {
+        self.find(pred).map(|p| p.len())
+    }
+
+    // 2つの経路が指すノードの最も近い共通の祖先までの経路　どちらかの経路が木の中になければNone
+    pub fn lca(&self, a: &NodePath, b: &NodePath) -> Option<NodePath> {
+        self.get_at(a)?;
+        self.get_at(b)?;
+
+        Some(a.common_prefix(b))
+    }
+}
+
+// 自動生成されるdropは子ノードを再帰的に解放するので、深い木ではスタックが溢れる
+// 子ノードを自前のスタックに移しながら1つずつ解放する
+impl<T> Drop for Child<T> {
+    fn drop(&mut self) {
+        let mut stack: Vec<Box<Node<T>>> = Vec::new();
+        stack.extend(self.0.take());
+
+        while let Some(mut node) = stack.pop() {
+            stack.extend(node.left.take());
+            stack.extend(node.right.take());
+        }   // ここで解放されるnodeは子を持たない
+    }
+}
+
+impl<T: Clone> Clone for Node<T> {
+    fn clone(&self) -> Self {
+        // 帰りがけ順にコピーし、コピー済みの部分木をdoneに積んでおく
+        // 親をコピーするときには、その子のコピーがdoneの末尾に（左→右の順で）並んでいる
+        let mut stack: Vec<(&Node<T>, bool)> = vec![(self, false)];
+        let mut done: Vec<Node<T>> = Vec::new();
+
+        while let Some((node, expanded)) = stack.pop() {
+            if !expanded {
+                stack.push((node, true));
+                if let Some(r) = node.right() {
+                    stack.push((r, false));
                 }
-                if self.right_mut().is_some() {
-                    self.right_mut().unwrap().foreach_mut(order, func);
+                if let Some(l) = node.left() {
+                    stack.push((l, false));
                 }
-                func(self.as_mut());
+                continue;
             }
+
+            let right = node.right.as_ref().map(|_| Box::new(done.pop().unwrap()));
+            let left = node.left.as_ref().map(|_| Box::new(done.pop().unwrap()));
+
+            done.push(Node {
+                data: node.data.clone(),
+                left: left.into(),
+                right: right.into(),
+            });
         }
-    }
 
-    pub fn iter<'r>(&'r self, order: &SearchOrder) -> NodeIter<T> {
-        let mut elems: Vec<&'r T> = Vec::new();
+        done.pop().unwrap()
+    }
+}
 
-        self.foreach(order, &mut |x| elems.push(x));
-        let length = elems.len();
+// 形と、同じ位置の要素がすべて等しいときに等しい
+impl<T: PartialEq> PartialEq for Node<T> {
+    fn eq(&self, other: &Self) -> bool {
+        let mut stack = vec![(self, other)];
 
-        NodeIter {
-            elements: elems,
-            len: length,
-            idx: 0,
+        while let Some((a, b)) = stack.pop() {
+            if a.data != b.data {
+                return false;
+            }
+            for (x, y) in [(a.left(), b.left()), (a.right(), b.right())].iter() {
+                match (x, y) {
+                    (Some(x), Some(y)) => stack.push((x, y)),
+                    (None, None) => (),
+                    _ => return false,
+                }
+            }
         }
+        true
     }
-/*
-    pub fn iter_mut<'r>(&'r self, order: &SearchOrder) -> NodeIterMut<T> {
-        let mut elems: Vec<&'r mut T> = Vec::new();
+}
 
-        self.foreach_mut(order, &mut |x| elems.push(x));
-        let length = elems.len();
+impl<T: Eq> Eq for Node<T> {}
 
-        let mut elems = elems.iter_mut();
-        
-        NodeIterMut {
-            elements: elems,
-            len: length,
-            idx: 0,
+// 行きがけ順に、要素と子の有無を流し込む
+impl<T: Hash> Hash for Node<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let mut stack = vec![self];
+
+        while let Some(node) = stack.pop() {
+            node.data.hash(state);
+            node.left.is_some().hash(state);
+            node.right.is_some().hash(state);
+
+            stack.extend(node.right());
+            stack.extend(node.left());
         }
     }
-    */
 }
 
 impl<T> AsRef<T> for Node<T> {
@@ -251,51 +534,283 @@ impl<T> AsMut<T> for Node<T> {
     }
 }
 
+// スタックに積む要素　未展開のノード or 次に返すデータ
+enum Step<'r, T> {
+    Node(&'r Node<T>),
+    Data(&'r T),
+}
+
+// 明示的なスタックで木をたどる　mirror = true のときは右→左の順に子をたどる
+// LevelOrderのときはstackをキューとして使う
+struct Walker<'r, T> {
+    stack: VecDeque<Step<'r, T>>,
+    order: SearchOrder,
+    mirror: bool,
+}
+
+impl<'r, T> Walker<'r, T> {
+    fn new(root: &'r Node<T>, order: SearchOrder, mirror: bool) -> Self {
+        Walker {
+            stack: vec![Step::Node(root)].into(),
+            order,
+            mirror,
+        }
+    }
+
+    // レベル順の逆（深い段から、各段は右→左）はキューでは作れないので、先に全要素を並べておいて後ろから返す
+    fn rev_level(root: &'r Node<T>) -> Self {
+        let mut walker = Walker::new(root, SearchOrder::LevelOrder, false);
+        let mut stack = VecDeque::new();
+
+        while let Some(x) = walker.next() {
+            stack.push_back(Step::Data(x));
+        }
+
+        Walker {
+            stack,
+            order: SearchOrder::LevelOrder,
+            mirror: true,
+        }
+    }
+
+    fn pop(&mut self) -> Option<Step<'r, T>> {
+        match self.order {
+            SearchOrder::LevelOrder if !self.mirror => self.stack.pop_front(),
+            _ => self.stack.pop_back(),
+        }
+    }
+
+    fn next(&mut self) -> Option<&'r T> {
+        while let Some(step) = self.pop() {
+            let node = match step {
+                Step::Data(d) => return Some(d),
+                Step::Node(n) => n,
+            };
+
+            let (first, second) = if self.mirror {
+                (node.right(), node.left())
+            } else {
+                (node.left(), node.right())
+            };
+
+            // スタックなので訪問する順番と逆に積む
+            match self.order {
+                SearchOrder::PreOrder => {
+                    self.push(second);
+                    self.push(first);
+                    return Some(&node.data);
+                },
+                SearchOrder::InOrder => {
+                    self.push(second);
+                    self.stack.push_back(Step::Data(&node.data));
+                    self.push(first);
+                },
+                SearchOrder::PostOrder => {
+                    self.stack.push_back(Step::Data(&node.data));
+                    self.push(second);
+                    self.push(first);
+                },
+                SearchOrder::LevelOrder => {
+                    self.push(first);
+                    self.push(second);
+                    return Some(&node.data);
+                },
+            }
+        }
+        None
+    }
+
+    fn push(&mut self, node: Option<&'r Node<T>>) {
+        if let Some(n) = node {
+            self.stack.push_back(Step::Node(n));
+        }
+    }
+}
+
 pub struct NodeIter<'r, T> {
-    elements: Vec<&'r T>,
-    len: usize,
-    idx: usize,
+    root: &'r Node<T>,
+    order: SearchOrder,
+    front: Walker<'r, T>,
+    back: Option<Walker<'r, T>>,    // next_back()が最初に呼ばれたときに作る
+    total: Cell<Option<usize>>,     // ノード数　最初に必要になったときに一度だけ数える
+    yielded: usize,                 // 前後から返した要素数の合計
+}
+
+impl<'r, T> NodeIter<'r, T> {
+    fn total(&self) -> usize {
+        match self.total.get() {
+            Some(n) => n,
+            None => {
+                let mut walker = Walker::new(self.root, SearchOrder::PreOrder, false);
+                let mut n = 0;
+                while walker.next().is_some() {
+                    n += 1;
+                }
+                self.total.set(Some(n));
+                n
+            }
+        }
+    }
+
+    fn exhausted(&self) -> bool {
+        match self.total.get() {
+            Some(n) => self.yielded >= n,
+            None => false,
+        }
+    }
 }
 
 impl<'r, T> Iterator for NodeIter<'r, T> {
     type Item = &'r T;
 
     fn next(&mut self) -> Option<&'r T> {
-        if self.idx < self.len {
-            let ret = Some(self.elements[self.idx]);
-            self.idx += 1;
-            ret
+        if self.exhausted() {
+            return None;
+        }
+
+        let ret = self.front.next();
+        if ret.is_some() {
+            self.yielded += 1;
         }
-        else {
-            None
+        ret
+    }
+
+    // ノード数は最初の呼び出しで一度だけ数え、以降は覚えておいた値を使う
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remain = self.total() - self.yielded;
+        (remain, Some(remain))
+    }
+}
+
+impl<'r, T> DoubleEndedIterator for NodeIter<'r, T> {
+    fn next_back(&mut self) -> Option<&'r T> {
+        // 後ろから返す順番は、左右を入れ替えた木を逆の順番でたどったものになる
+        //   PreOrder(ノード→左→右)の逆 = 右→左→ノード
+        //   InOrder(左→ノード→右)の逆 = 右→ノード→左
+        //   PostOrder(左→右→ノード)の逆 = ノード→右→左
+        if self.back.is_none() {
+            self.total();   // 前後が出会う位置を知るためにノード数を確定させる
+            self.back = Some(match self.order {
+                SearchOrder::PreOrder => Walker::new(self.root, SearchOrder::PostOrder, true),
+                SearchOrder::InOrder => Walker::new(self.root, SearchOrder::InOrder, true),
+                SearchOrder::PostOrder => Walker::new(self.root, SearchOrder::PreOrder, true),
+                SearchOrder::LevelOrder => Walker::rev_level(self.root),
+            });
+        }
+
+        if self.exhausted() {
+            return None;
+        }
+
+        let ret = self.back.as_mut().unwrap().next();
+        if ret.is_some() {
+            self.yielded += 1;
         }
+        ret
     }
 }
 
+impl<'r, T> ExactSizeIterator for NodeIter<'r, T> {}
+
+impl<'r, T> FusedIterator for NodeIter<'r, T> {}
+
+enum StepMut<'r, T> {
+    Node(&'r mut Node<T>),
+    Data(&'r mut T),
+}
+
+// ノードを data / left / right に分解してから積むので、同じ要素への &mut が重複することはない
 pub struct NodeIterMut<'r, T> {
-    elements: std::slice::IterMut<'r, &'r mut T>,
-    len: usize,
-    idx: usize,
+    stack: VecDeque<StepMut<'r, T>>,
+    order: SearchOrder,
+}
+
+impl<'r, T> NodeIterMut<'r, T> {
+    fn push(&mut self, node: &'r mut Option<Box<Node<T>>>) {
+        if let Some(n) = node.as_deref_mut() {
+            self.stack.push_back(StepMut::Node(n));
+        }
+    }
 }
-/*
+
 impl<'r, T> Iterator for NodeIterMut<'r, T> {
     type Item = &'r mut T;
 
     fn next(&mut self) -> Option<&'r mut T> {
-        if self.idx < self.len {
-            let a = self.elements[self.idx];
-            let ret = Some(a);
-            self.idx += 1;
-            ret
+        loop {
+            let step = match self.order {
+                SearchOrder::LevelOrder => self.stack.pop_front()?,
+                _ => self.stack.pop_back()?,
+            };
+            let node = match step {
+                StepMut::Data(d) => return Some(d),
+                StepMut::Node(n) => n,
+            };
+
+            let Node { data, left, right } = node;
+
+            match self.order {
+                SearchOrder::PreOrder => {
+                    self.push(right);
+                    self.push(left);
+                    return Some(data);
+                },
+                SearchOrder::InOrder => {
+                    self.push(right);
+                    self.stack.push_back(StepMut::Data(data));
+                    self.push(left);
+                },
+                SearchOrder::PostOrder => {
+                    self.stack.push_back(StepMut::Data(data));
+                    self.push(right);
+                    self.push(left);
+                },
+                SearchOrder::LevelOrder => {
+                    self.push(left);
+                    self.push(right);
+                    return Some(data);
+                },
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        // スタックの各要素は少なくとも1つの要素を返す
+        (self.stack.len(), None)
+    }
+}
+
+impl<'r, T> FusedIterator for NodeIterMut<'r, T> {}
+
+pub struct DepthIter<'r, T> {
+    queue: VecDeque<(usize, &'r Node<T>)>,
+}
+
+impl<'r, T> Iterator for DepthIter<'r, T> {
+    type Item = (usize, &'r T);
+
+    fn next(&mut self) -> Option<(usize, &'r T)> {
+        let (depth, node) = self.queue.pop_front()?;
+
+        if let Some(l) = node.left() {
+            self.queue.push_back((depth + 1, l));
         }
-        else {
-            None
+        if let Some(r) = node.right() {
+            self.queue.push_back((depth + 1, r));
         }
+        Some((depth, &node.data))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.queue.len(), None)
     }
 }
-*/
+
+impl<'r, T> FusedIterator for DepthIter<'r, T> {}
+
 #[cfg(test)]
 mod tests {
+    use crate::tree::test_util::create_test_tree;
 
     #[derive(Debug, Eq, PartialEq)]
     struct Ijk {
@@ -493,23 +1008,6 @@ mod tests {
 
     }
 
-    fn create_test_tree() -> Node<i32> {
-        let mut root = Node::new(1);
-        let mut left = Node::new(2);
-        let mut right = Node::new(3);
-
-        left.add_node_left(Node::new(4)).unwrap();
-        left.add_node_right(Node::new(5)).unwrap();
-
-        right.add_node_left(Node::new(6)).unwrap();
-        right.add_node_right(Node::new(7)).unwrap();
-
-        root.add_node_left(left).unwrap();
-        root.add_node_right(right).unwrap();
-
-        root
-    }
-
     #[test]
     fn foreach_mutable_test()
     {
@@ -542,11 +1040,8 @@ mod tests {
         root.add_node_left(left).unwrap();
         root.add_node_right(right).unwrap();
 
-        let iter = root.iter(&SearchOrder::PreOrder);
-        let mut sum = 0;
-        iter.map(|x| sum += x);
-        
-        /*
+        let mut iter = root.iter(&SearchOrder::PreOrder);
+
         assert_eq!(iter.next(), Some(&1));
         assert_eq!(iter.next(), Some(&2));
         assert_eq!(iter.next(), Some(&4));
@@ -554,7 +1049,332 @@ mod tests {
         assert_eq!(iter.next(), Some(&3));
         assert_eq!(iter.next(), Some(&6));
         assert_eq!(iter.next(), Some(&7));
-        assert_eq!(iter.next(), None);*/
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn iterator_order_test() {
+        let root = create_test_tree();
+
+        let preorder: Vec<i32> = root.iter(&SearchOrder::PreOrder).cloned().collect();
+        let inorder: Vec<i32> = root.iter(&SearchOrder::InOrder).cloned().collect();
+        let postorder: Vec<i32> = root.iter(&SearchOrder::PostOrder).cloned().collect();
+
+        assert_eq!(preorder, vec![1, 2, 4, 5, 3, 6, 7]);
+        assert_eq!(inorder, vec![4, 2, 5, 1, 6, 3, 7]);
+        assert_eq!(postorder, vec![4, 5, 2, 6, 7, 3, 1]);
+
+        // 片側にだけ伸びた木
+        let mut chain = Node::new(1);
+        chain.create_left_node(2).unwrap().create_right_node(3).unwrap().create_left_node(4).unwrap();
+
+        let inorder: Vec<i32> = chain.iter(&SearchOrder::InOrder).cloned().collect();
+        assert_eq!(inorder, vec![2, 4, 3, 1]);
+    }
+
+    #[test]
+    fn iterator_rev_test() {
+        let root = create_test_tree();
+
+        for order in &[SearchOrder::PreOrder, SearchOrder::InOrder, SearchOrder::PostOrder, SearchOrder::LevelOrder] {
+            let mut forward: Vec<i32> = root.iter(order).cloned().collect();
+            let backward: Vec<i32> = root.iter(order).rev().cloned().collect();
+
+            forward.reverse();
+            assert_eq!(forward, backward);
+        }
+
+        // 前後から交互に取り出しても重複しない
+        let mut iter = root.iter(&SearchOrder::InOrder);
+        assert_eq!(iter.next(), Some(&4));
+        assert_eq!(iter.next_back(), Some(&7));
+        assert_eq!(iter.next(), Some(&2));
+        assert_eq!(iter.next_back(), Some(&3));
+        assert_eq!(iter.len(), 3);
+        assert_eq!(iter.next(), Some(&5));
+        assert_eq!(iter.next_back(), Some(&6));
+        assert_eq!(iter.next(), Some(&1));
+        assert_eq!(iter.next_back(), None);
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn iterator_size_hint_test() {
+        let root = create_test_tree();
+
+        let mut iter = root.iter(&SearchOrder::PostOrder);
+        assert_eq!(iter.size_hint(), (7, Some(7)));
+
+        iter.next();
+        iter.next();
+        assert_eq!(iter.len(), 5);
+
+        iter.next_back();
+        assert_eq!(iter.len(), 4);
+
+        iter.by_ref().for_each(drop);
+        assert_eq!(iter.len(), 0);
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn iterator_early_stop_test() {
+        let root = create_test_tree();
+
+        let found = root.iter(&SearchOrder::InOrder).find(|x| **x > 4);
+        assert_eq!(found, Some(&5));
+
+        let firsts: Vec<&i32> = root.iter(&SearchOrder::PostOrder).take(3).collect();
+        assert_eq!(firsts, vec![&4, &5, &2]);
+    }
+
+    #[test]
+    fn iterator_mut_test() {
+        for order in &[SearchOrder::PreOrder, SearchOrder::InOrder, SearchOrder::PostOrder, SearchOrder::LevelOrder] {
+            let mut root = create_test_tree();
+
+            // 訪問順に番号を振り直すと、iter()の結果は 0, 1, 2, ... になる
+            for (i, x) in root.iter_mut(order).enumerate() {
+                *x = i as i32;
+            }
+
+            let result: Vec<i32> = root.iter(order).cloned().collect();
+            assert_eq!(result, vec![0, 1, 2, 3, 4, 5, 6]);
+        }
+    }
+
+    #[test]
+    fn iterator_mut_adapter_test() {
+        let mut root = create_test_tree();
+
+        root.iter_mut(&SearchOrder::InOrder)
+            .filter(|x| **x % 2 == 0)
+            .for_each(|x| *x *= 10);
+        let result: Vec<i32> = root.iter(&SearchOrder::InOrder).cloned().collect();
+        assert_eq!(result, vec![40, 20, 5, 1, 60, 3, 7]);
+
+        let mut root = create_test_tree();
+        let offsets = [100, 200, 300];
+
+        for (x, o) in root.iter_mut(&SearchOrder::PreOrder).zip(offsets.iter()) {
+            *x += o;
+        }
+        let result: Vec<i32> = root.iter(&SearchOrder::PreOrder).cloned().collect();
+        assert_eq!(result, vec![101, 202, 304, 5, 3, 6, 7]);
+
+        let mut root = create_test_tree();
+
+        root.iter_mut(&SearchOrder::PostOrder)
+            .take_while(|x| **x != 6)
+            .for_each(|x| *x = 0);
+        let result: Vec<i32> = root.iter(&SearchOrder::PostOrder).cloned().collect();
+        assert_eq!(result, vec![0, 0, 0, 6, 7, 3, 1]);
+    }
+
+    #[test]
+    fn level_order_test() {
+        let mut root = create_test_tree();
+        root.right_mut().unwrap().take_left();
+        root.left_mut().unwrap().left_mut().unwrap().create_right_node(8).unwrap();
+
+        let levelorder = vec![1, 2, 3, 4, 5, 7, 8];
+
+        let mut result: Vec<i32> = Vec::new();
+        root.foreach(&SearchOrder::LevelOrder, &mut |x: &i32| result.push(*x));
+        assert_eq!(result, levelorder);
+
+        let result: Vec<i32> = root.iter(&SearchOrder::LevelOrder).cloned().collect();
+        assert_eq!(result, levelorder);
+
+        let result: Vec<i32> = root.iter(&SearchOrder::LevelOrder).rev().cloned().collect();
+        assert_eq!(result, vec![8, 7, 5, 4, 3, 2, 1]);
+
+        let mut cnt = 0;
+        root.foreach_mut(&SearchOrder::LevelOrder, &mut |x| { *x = cnt; cnt += 1; });
+        let result: Vec<i32> = root.iter(&SearchOrder::PreOrder).cloned().collect();
+        assert_eq!(result, vec![0, 1, 3, 6, 4, 2, 5]);
+    }
+
+    #[test]
+    fn depth_test() {
+        let mut root = create_test_tree();
+        root.right_mut().unwrap().take_left();
+        root.left_mut().unwrap().left_mut().unwrap().create_right_node(8).unwrap();
+
+        let result: Vec<(usize, i32)> = root.iter_with_depth().map(|(d, x)| (d, *x)).collect();
+        assert_eq!(result, vec![(0, 1), (1, 2), (1, 3), (2, 4), (2, 5), (2, 7), (3, 8)]);
+
+        let levels = root.levels();
+        assert_eq!(levels, vec![vec![&1], vec![&2, &3], vec![&4, &5, &7], vec![&8]]);
+    }
+
+    fn create_deep_tree(depth: i32) -> Node<i32> {
+        let mut root = Node::new(0);
+        for i in 1..depth {
+            let mut node = Node::new(i);
+            node.replace_left(root);
+            root = node;
+        }
+        root
+    }
+
+    #[test]
+    fn clone_test() {
+        let root = create_test_tree();
+        let mut copied = root.clone();
+
+        *copied.as_mut() = 0;
+
+        let result: Vec<i32> = copied.iter(&SearchOrder::PreOrder).cloned().collect();
+        assert_eq!(result, vec![0, 2, 4, 5, 3, 6, 7]);
+        let result: Vec<i32> = root.iter(&SearchOrder::PreOrder).cloned().collect();
+        assert_eq!(result, vec![1, 2, 4, 5, 3, 6, 7]);
+    }
+
+    #[test]
+    fn eq_hash_test() {
+        use std::collections::hash_map::DefaultHasher;
+
+        let hash = |n: &Node<i32>| {
+            let mut h = DefaultHasher::new();
+            n.hash(&mut h);
+            h.finish()
+        };
+
+        let root = create_test_tree();
+        let mut other = root.clone();
+        assert_eq!(root, other);
+        assert_eq!(hash(&root), hash(&other));
+
+        *other.right_mut().unwrap().as_mut() = 30;
+        assert_ne!(root, other);
+
+        // 要素が同じでも形が違えば等しくない
+        let mut a = Node::new(1);
+        a.create_left_node(2).unwrap();
+        let mut b = Node::new(1);
+        b.create_right_node(2).unwrap();
+        assert_ne!(a, b);
+        assert_ne!(hash(&a), hash(&b));
+    }
+
+    #[test]
+    fn deep_tree_test() {
+        const DEPTH: i32 = 1_000_000;
+
+        let mut root = create_deep_tree(DEPTH);
+
+        let mut cnt = 0;
+        root.foreach(&SearchOrder::PreOrder, &mut |_| cnt += 1);
+        assert_eq!(cnt, DEPTH);
+
+        root.foreach_mut(&SearchOrder::InOrder, &mut |x| *x += 1);
+        assert_eq!(root.iter(&SearchOrder::InOrder).next(), Some(&1));
+        assert_eq!(root.iter(&SearchOrder::PostOrder).count(), DEPTH as usize);
+
+        let copied = root.clone();
+        assert_eq!(copied.iter(&SearchOrder::PreOrder).next_back(), Some(&1));
+        assert!(copied == root);
+
+        drop(copied);
+        drop(root);
+
+        // 右に伸びた木、左右交互に伸びた木も同様に解放できる
+        let mut root = Node::new(0);
+        let mut cur = &mut root;
+        for i in 1..DEPTH {
+            cur = if i % 3 == 0 { cur.create_left_node(i).unwrap() } else { cur.create_right_node(i).unwrap() };
+        }
+        assert_eq!(root.clone().iter(&SearchOrder::PostOrder).next(), Some(&(DEPTH - 1)));
+
+        // 要素だけを取り出したときも、残った子は同じように解放される
+        assert_eq!(root.into_data(), 0);
+    }
+
+    #[test]
+    fn rotate_test() {
+        let mut root = create_test_tree();
+
+        root.rotate_left().unwrap();
+        let result: Vec<i32> = root.iter(&SearchOrder::PreOrder).cloned().collect();
+        assert_eq!(result, vec![3, 1, 2, 4, 5, 6, 7]);
+        // 回転しても通りがけ順は変わらない
+        let result: Vec<i32> = root.iter(&SearchOrder::InOrder).cloned().collect();
+        assert_eq!(result, vec![4, 2, 5, 1, 6, 3, 7]);
+
+        root.rotate_right().unwrap();
+        let result: Vec<i32> = root.iter(&SearchOrder::PreOrder).cloned().collect();
+        assert_eq!(result, vec![1, 2, 4, 5, 3, 6, 7]);
+
+        root.rotate_right().unwrap();
+        let result: Vec<i32> = root.iter(&SearchOrder::PreOrder).cloned().collect();
+        assert_eq!(result, vec![2, 4, 1, 5, 3, 6, 7]);
+
+        let mut leaf = Node::new(0);
+        assert_eq!(leaf.rotate_left(), Err(NodeError::RotateError));
+        assert_eq!(leaf.rotate_right(), Err(NodeError::RotateError));
+    }
+
+    #[test]
+    fn metrics_test() {
+        let root = create_test_tree();
+
+        assert_eq!(root.height(), 3);
+        assert_eq!(root.len(), 7);
+        assert_eq!(root.leaf_count(), 4);
+        assert!(root.is_full());
+        assert!(root.is_complete());
+        assert!(root.is_balanced());
+        assert!(!root.is_leaf());
+
+        // 4の右に子を足すと、全二分木でも完全二分木でもなくなるが、まだ平衡している
+        let mut root = create_test_tree();
+        root.left_mut().unwrap().left_mut().unwrap().create_right_node(8).unwrap();
+        assert_eq!(root.height(), 4);
+        assert_eq!(root.len(), 8);
+        assert_eq!(root.leaf_count(), 4);
+        assert!(!root.is_full());
+        assert!(!root.is_complete());
+        assert!(root.is_balanced());
+
+        // 最後の段が左から詰まっていれば完全二分木
+        let mut root = create_test_tree();
+        root.right_mut().unwrap().take_right();
+        assert!(root.is_complete());
+        root.right_mut().unwrap().take_left();
+        assert!(root.is_complete());
+        root.left_mut().unwrap().take_left();
+        assert!(!root.is_complete());
+
+        let deep = create_deep_tree(4);
+        assert_eq!(deep.height(), 4);
+        assert_eq!(deep.leaf_count(), 1);
+        assert!(!deep.is_balanced());
+        assert!(Node::new(0).is_full());
+    }
+
+    #[test]
+    fn find_test() {
+        let root = create_test_tree();
+
+        assert_eq!(root.find(|x| *x == 1), Some(NodePath::root()));
+        assert_eq!(root.find(|x| *x == 5), Some(NodePath::from(vec![Side::Left, Side::Right])));
+        assert_eq!(root.find(|x| *x == 6), Some(NodePath::from(vec![Side::Right, Side::Left])));
+        assert_eq!(root.find(|x| *x > 4), Some(NodePath::from(vec![Side::Left, Side::Right])));
+        assert_eq!(root.find(|x| *x == 9), None);
+        assert_eq!(root.depth_of(|x| *x == 3), Some(1));
+
+        let four = root.find(|x| *x == 4).unwrap();
+        let five = root.find(|x| *x == 5).unwrap();
+        let seven = root.find(|x| *x == 7).unwrap();
+
+        let left = NodePath::root().join(Side::Left);
+
+        assert_eq!(root.lca(&four, &five), Some(left.clone()));
+        assert_eq!(root.lca(&four, &seven), Some(NodePath::root()));
+        assert_eq!(root.lca(&four, &left), Some(left.clone()));
+        assert_eq!(root.lca(&four, &four), Some(four.clone()));
+        assert_eq!(root.lca(&four, &four.join(Side::Left)), None);
     }
 
     #[test]
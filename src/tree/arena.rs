@@ -0,0 +1,400 @@
+use super::{Node, NodeError, Side};
+
+// ArenaTreeの中のノードを指すID　削除されたノードのIDは再利用しない
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, PartialOrd, Ord)]
+pub struct NodeId(usize);
+
+impl NodeId {
+    // 0から振られる通し番号　ノードごとの情報を別のVecに持たせるときの添字に使える
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Slot<T> {
+    data: T,
+    parent: Option<NodeId>,
+    left: Option<NodeId>,
+    right: Option<NodeId>,
+}
+
+// ノードをVecにまとめて持つ二分木　ノードごとにBoxを確保せず、親へのリンクも持つ
+// 根に繋がっていない部分木(切り離したノードなど)も同じアリーナの中に置いておける
+#[derive(Debug, Clone)]
+pub struct ArenaTree<T> {
+    slots: Vec<Option<Slot<T>>>,
+    root: Option<NodeId>,
+    len: usize,
+}
+
+impl<T> ArenaTree<T> {
+    pub fn new() -> Self {
+        ArenaTree {
+            slots: Vec::new(),
+            root: None,
+            len: 0,
+        }
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        ArenaTree {
+            slots: Vec::with_capacity(capacity),
+            root: None,
+            len: 0,
+        }
+    }
+
+    // 根に繋がっていないノードも含めた、生きているノードの数
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn clear(&mut self) {
+        self.slots.clear();
+        self.root = None;
+        self.len = 0;
+    }
+
+    pub fn root(&self) -> Option<NodeId> {
+        self.root
+    }
+
+    // 親のないノードを根にする
+    pub fn set_root(&mut self, id: NodeId) -> Result<(), NodeError> {
+        if self.slot(id)?.parent.is_some() {
+            return Err( NodeError::AttachError );
+        }
+        self.root = Some(id);
+        Ok(())
+    }
+
+    // どこにも繋がっていないノードを作る
+    pub fn new_node(&mut self, data: T) -> NodeId {
+        let id = NodeId(self.slots.len());
+
+        self.slots.push(Some(Slot {
+            data,
+            parent: None,
+            left: None,
+            right: None,
+        }));
+        self.len += 1;
+        id
+    }
+
+    pub fn contains(&self, id: NodeId) -> bool {
+        self.slot(id).is_ok()
+    }
+
+    pub fn get(&self, id: NodeId) -> Option<&T> {
+        self.slot(id).ok().map(|s| &s.data)
+    }
+
+    pub fn get_mut(&mut self, id: NodeId) -> Option<&mut T> {
+        self.slot_mut(id).ok().map(|s| &mut s.data)
+    }
+
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.slot(id).ok()?.parent
+    }
+
+    pub fn left(&self, id: NodeId) -> Option<NodeId> {
+        self.slot(id).ok()?.left
+    }
+
+    pub fn right(&self, id: NodeId) -> Option<NodeId> {
+        self.slot(id).ok()?.right
+    }
+
+    pub fn child(&self, id: NodeId, side: Side) -> Option<NodeId> {
+        match side {
+            Side::Left => self.left(id),
+            Side::Right => self.right(id),
+        }
+    }
+
+    // 親のどちら側の子か　親がなければNone
+    pub fn side(&self, id: NodeId) -> Option<Side> {
+        let parent = self.parent(id)?;
+
+        if self.left(parent) == Some(id) {
+            Some(Side::Left)
+        } else {
+            Some(Side::Right)
+        }
+    }
+
+    // 親から順に、部分木の根まで返す
+    pub fn ancestors(&self, id: NodeId) -> Ancestors<'_, T> {
+        Ancestors {
+            tree: self,
+            next: self.parent(id),
+        }
+    }
+
+    pub fn attach_left(&mut self, parent: NodeId, child: NodeId) -> Result<(), NodeError> {
+        self.attach(parent, child, Side::Left)
+    }
+
+    pub fn attach_right(&mut self, parent: NodeId, child: NodeId) -> Result<(), NodeError> {
+        self.attach(parent, child, Side::Right)
+    }
+
+    // 切り離されている部分木childを、parentの空いている側に付ける
+    pub fn attach(&mut self, parent: NodeId, child: NodeId, side: Side) -> Result<(), NodeError> {
+        if self.slot(child)?.parent.is_some() || self.root == Some(child) {
+            return Err( NodeError::AttachError );
+        }
+        if self.child(parent, side).is_some() || !self.contains(parent) {
+            return Err( if self.contains(parent) { NodeError::ChildAddError } else { NodeError::NoNode } );
+        }
+        if parent == child || self.ancestors(parent).any(|a| a == child) {
+            return Err( NodeError::AttachError );
+        }
+
+        self.link(parent, child, side);
+        Ok(())
+    }
+
+    // 部分木を親から切り離す　ノードはアリーナに残るので、IDはそのまま使える
+    pub fn detach(&mut self, id: NodeId) -> Result<(), NodeError> {
+        match self.slot(id)?.parent {
+            Some(parent) => {
+                let p = self.slot_mut(parent)?;
+                if p.left == Some(id) {
+                    p.left = None;
+                } else {
+                    p.right = None;
+                }
+                self.slot_mut(id)?.parent = None;
+            },
+            None => {
+                if self.root == Some(id) {
+                    self.root = None;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Node<T>の木をアリーナに移し、その根のIDを返す(根には設定しない)
+    pub fn insert_node(&mut self, tree: Node<T>) -> NodeId {
+        let mut stack = vec![(tree, None)];
+        let mut top = None;
+
+        while let Some((mut node, link)) = stack.pop() {
+            let left = node.take_left();
+            let right = node.take_right();
+            let id = self.new_node(node.into_data());
+
+            match link {
+                Some((parent, side)) => self.link(parent, id, side),    // 作ったばかりのノードなので確認はいらない
+                None => top = Some(id),
+            }
+
+            if let Some(r) = right {
+                stack.push((r, Some((id, Side::Right))));
+            }
+            if let Some(l) = left {
+                stack.push((l, Some((id, Side::Left))));
+            }
+        }
+
+        top.unwrap()
+    }
+
+    // 部分木をアリーナから取り除き、Node<T>の木にして返す
+    pub fn take_node(&mut self, id: NodeId) -> Result<Node<T>, NodeError> {
+        self.detach(id)?;
+
+        // 帰りがけ順に組み立てる　子の部分木はdoneの末尾に左→右の順で並んでいる
+        let mut stack = vec![(id, false)];
+        let mut done: Vec<Node<T>> = Vec::new();
+
+        while let Some((cur, expanded)) = stack.pop() {
+            let (left, right) = {
+                let s = self.slot(cur)?;
+                (s.left, s.right)
+            };
+
+            if !expanded {
+                stack.push((cur, true));
+                stack.extend(right.map(|r| (r, false)));
+                stack.extend(left.map(|l| (l, false)));
+                continue;
+            }
+
+            let slot = self.slots[cur.0].take().unwrap();
+            self.len -= 1;
+
+            let mut node = Node::new(slot.data);
+            if right.is_some() {
+                *node.right = Some(Box::new(done.pop().unwrap()));
+            }
+            if left.is_some() {
+                *node.left = Some(Box::new(done.pop().unwrap()));
+            }
+            done.push(node);
+        }
+
+        Ok(done.pop().unwrap())
+    }
+
+    // 根から辿れる木をNode<T>にする　根に繋がっていないノードは捨てる
+    pub fn into_node(mut self) -> Option<Node<T>> {
+        let root = self.root?;
+        self.take_node(root).ok()
+    }
+
+    fn link(&mut self, parent: NodeId, child: NodeId, side: Side) {
+        let p = self.slots[parent.0].as_mut().unwrap();
+        match side {
+            Side::Left => p.left = Some(child),
+            Side::Right => p.right = Some(child),
+        }
+        self.slots[child.0].as_mut().unwrap().parent = Some(parent);
+    }
+
+    fn slot(&self, id: NodeId) -> Result<&Slot<T>, NodeError> {
+        match self.slots.get(id.0) {
+            Some(Some(s)) => Ok(s),
+            _ => Err( NodeError::NoNode ),
+        }
+    }
+
+    fn slot_mut(&mut self, id: NodeId) -> Result<&mut Slot<T>, NodeError> {
+        match self.slots.get_mut(id.0) {
+            Some(Some(s)) => Ok(s),
+            _ => Err( NodeError::NoNode ),
+        }
+    }
+}
+
+impl<T> Default for ArenaTree<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> From<Node<T>> for ArenaTree<T> {
+    fn from(tree: Node<T>) -> Self {
+        let mut arena = ArenaTree::new();
+        let root = arena.insert_node(tree);
+        arena.root = Some(root);
+        arena
+    }
+}
+
+pub struct Ancestors<'r, T> {
+    tree: &'r ArenaTree<T>,
+    next: Option<NodeId>,
+}
+
+impl<'r, T> Iterator for Ancestors<'r, T> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<NodeId> {
+        let id = self.next?;
+        self.next = self.tree.parent(id);
+        Some(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::SearchOrder;
+    use crate::tree::test_util::create_test_tree;
+
+    fn preorder(root: &Node<i32>) -> Vec<i32> {
+        root.iter(&SearchOrder::PreOrder).cloned().collect()
+    }
+
+    #[test]
+    fn from_node() {
+        let arena = ArenaTree::from(create_test_tree());
+        let root = arena.root().unwrap();
+
+        assert_eq!(arena.len(), 7);
+        assert_eq!(arena.get(root), Some(&1));
+
+        let five = arena.right(arena.left(root).unwrap()).unwrap();
+        assert_eq!(arena.get(five), Some(&5));
+        assert_eq!(arena.side(five), Some(Side::Right));
+        assert_eq!(arena.ancestors(five).map(|id| *arena.get(id).unwrap()).collect::<Vec<i32>>(), vec![2, 1]);
+        assert_eq!(arena.parent(root), None);
+
+        assert_eq!(preorder(&arena.into_node().unwrap()), vec![1, 2, 4, 5, 3, 6, 7]);
+    }
+
+    #[test]
+    fn build_and_edit() {
+        let mut arena = ArenaTree::with_capacity(4);
+        let a = arena.new_node("a");
+        let b = arena.new_node("b");
+        let c = arena.new_node("c");
+
+        arena.set_root(a).unwrap();
+        arena.attach_left(a, b).unwrap();
+        arena.attach_right(b, c).unwrap();
+
+        assert_eq!(arena.attach_right(a, b), Err(NodeError::AttachError));      // 既に親がある
+        assert_eq!(arena.attach_left(a, c), Err(NodeError::AttachError));
+        let d = arena.new_node("d");
+        assert_eq!(arena.attach_left(a, d), Err(NodeError::ChildAddError));
+
+        // 自分の子孫の下に付けると循環するので付けられない
+        arena.detach(b).unwrap();
+        assert_eq!(arena.attach_left(c, b), Err(NodeError::AttachError));
+        assert_eq!(arena.parent(b), None);
+        assert_eq!(arena.left(a), None);
+
+        arena.attach_right(a, b).unwrap();
+        arena.attach_left(a, d).unwrap();
+        *arena.get_mut(d).unwrap() = "D";
+
+        let node = arena.into_node().unwrap();
+        let result: Vec<&str> = node.iter(&SearchOrder::InOrder).cloned().collect();
+        assert_eq!(result, vec!["D", "a", "b", "c"]);
+    }
+
+    #[test]
+    fn take_and_insert_node() {
+        let mut arena = ArenaTree::from(create_test_tree());
+        let root = arena.root().unwrap();
+        let two = arena.left(root).unwrap();
+
+        let sub = arena.take_node(two).unwrap();
+        assert_eq!(preorder(&sub), vec![2, 4, 5]);
+        assert_eq!(arena.len(), 4);
+        assert_eq!(arena.get(two), None);               // 取り除いたIDは無効になる
+        assert_eq!(arena.take_node(two).unwrap_err(), NodeError::NoNode);
+
+        let new_id = arena.insert_node(sub);
+        assert_ne!(new_id, two);
+        arena.attach_left(root, new_id).unwrap();
+        assert_eq!(arena.len(), 7);
+
+        assert_eq!(preorder(&arena.into_node().unwrap()), vec![1, 2, 4, 5, 3, 6, 7]);
+    }
+
+    #[test]
+    fn deep_tree() {
+        let mut root = Node::new(0);
+        let mut cur = &mut root;
+        for i in 1..100_000 {
+            cur = cur.create_left_node(i).unwrap();
+        }
+
+        let arena = ArenaTree::from(root);
+        assert_eq!(arena.len(), 100_000);
+
+        let node = arena.into_node().unwrap();
+        assert_eq!(node.iter(&SearchOrder::InOrder).next(), Some(&99_999));
+    }
+}
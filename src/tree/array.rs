@@ -0,0 +1,148 @@
+use std::cmp;
+use std::collections::VecDeque;
+
+use super::{Node, RebuildError};
+
+const MIN_LAYOUT_LIMIT: usize = 1 << 20;
+
+// 配列による木の表現　位置iのノードの子は2i+1(左)と2i+2(右)、ないノードはNone
+// 完全2分木ならNoneのない詰まった配列になる
+// 偏った木では配列の長さが高さに対して指数的に増えるので、長さが上限を超える木はTooDeepにする
+impl<T: Clone> Node<T> {
+    pub fn to_array_layout(&self) -> Result<Vec<Option<T>>, RebuildError> {
+        let mut array: Vec<Option<T>> = (0..self.layout_len()?).map(|_| None).collect();
+        let mut queue = VecDeque::new();
+        queue.push_back((self, 0));
+
+        while let Some((node, idx)) = queue.pop_front() {
+            array[idx] = Some(node.data.clone());
+            queue.extend(node.left().map(|l| (l, 2 * idx + 1)));
+            queue.extend(node.right().map(|r| (r, 2 * idx + 2)));
+        }
+
+        Ok(array)
+    }
+}
+
+impl<T> Node<T> {
+    pub fn into_array_layout(self) -> Result<Vec<Option<T>>, RebuildError> {
+        let mut array: Vec<Option<T>> = (0..self.layout_len()?).map(|_| None).collect();
+        let mut queue = VecDeque::new();
+        queue.push_back((self, 0));
+
+        while let Some((mut node, idx)) = queue.pop_front() {
+            queue.extend(node.take_left().map(|l| (l, 2 * idx + 1)));
+            queue.extend(node.take_right().map(|r| (r, 2 * idx + 2)));
+            array[idx] = Some(node.into_data());
+        }
+
+        Ok(array)
+    }
+
+    // 配列にしたときの長さ　確保する前に求めておく
+    // 完全2分木なら長さはノード数の2倍未満なので、それと小さな木のための下限の大きい方を上限にする
+    fn layout_len(&self) -> Result<usize, RebuildError> {
+        let limit = cmp::max(2 * self.len(), MIN_LAYOUT_LIMIT);
+        let mut len = 0;
+        let mut queue = VecDeque::new();
+        queue.push_back((self, 0, 0));
+
+        while let Some((node, idx, depth)) = queue.pop_front() {
+            if idx >= limit {
+                return Err( RebuildError::TooDeep(depth) );
+            }
+            len = cmp::max(len, idx + 1);
+            queue.extend(node.left().map(|l| (l, 2 * idx + 1, depth + 1)));
+            queue.extend(node.right().map(|r| (r, 2 * idx + 2, depth + 1)));
+        }
+
+        Ok(len)
+    }
+
+    pub fn from_array_layout(array: Vec<Option<T>>) -> Result<Node<T>, RebuildError> {
+        match array.first() {
+            None => return Err( RebuildError::Empty ),
+            Some(None) => return Err( RebuildError::RootHole ),
+            Some(Some(_)) => (),
+        }
+        for i in 1..array.len() {
+            if array[i].is_some() && array[(i - 1) / 2].is_none() {
+                return Err( RebuildError::Orphan(i) );
+            }
+        }
+
+        // 子の方が位置が大きいので、後ろから組み立てる
+        let mut slots: Vec<Option<Node<T>>> = (0..array.len()).map(|_| None).collect();
+        for (i, data) in array.into_iter().enumerate().rev() {
+            if let Some(data) = data {
+                let left = slots.get_mut(2 * i + 1).and_then(|s| s.take());
+                let right = slots.get_mut(2 * i + 2).and_then(|s| s.take());
+                slots[i] = Some(Node {
+                    data,
+                    left: left.map(Box::new).into(),
+                    right: right.map(Box::new).into(),
+                });
+            }
+        }
+
+        Ok(slots[0].take().unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::SearchOrder;
+    use crate::tree::test_util::create_test_tree;
+
+    #[test]
+    fn complete_tree() {
+        let root = create_test_tree();
+        let array = root.to_array_layout().unwrap();
+        assert_eq!(array, (1..=7).map(Some).collect::<Vec<_>>());
+
+        let back = Node::from_array_layout(array).unwrap();
+        assert_eq!(back.to_sexpr(), "(1 (2 4 5) (3 6 7))");
+        assert_eq!(back.into_array_layout(), root.to_array_layout());
+    }
+
+    #[test]
+    fn sparse_tree() {
+        let root: Node<i32> = Node::from_sexpr("(1 (2 _ 5) 3)").unwrap();
+        let array = root.to_array_layout().unwrap();
+        assert_eq!(array, vec![Some(1), Some(2), Some(3), None, Some(5)]);
+
+        let back = Node::from_array_layout(array).unwrap();
+        assert!(back.iter(&SearchOrder::PreOrder).eq(root.iter(&SearchOrder::PreOrder)));
+        assert!(back.left().unwrap().left().is_none());
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(Node::<i32>::from_array_layout(vec![]).unwrap_err(), RebuildError::Empty);
+        assert_eq!(Node::from_array_layout(vec![None, Some(1)]).unwrap_err(), RebuildError::RootHole);
+        assert_eq!(Node::from_array_layout(vec![Some(1), None, Some(2), Some(3)]).unwrap_err(), RebuildError::Orphan(3));
+    }
+
+    #[test]
+    fn too_deep() {
+        // 右に40段伸びた鎖は2^40個以上の要素が必要になる
+        let mut root = Node::new(0);
+        let mut cur = &mut root;
+        for i in 1..40 {
+            cur = cur.create_right_node(i).unwrap();
+        }
+        assert_eq!(root.to_array_layout().unwrap_err(), RebuildError::TooDeep(20));
+        assert_eq!(root.into_array_layout().unwrap_err(), RebuildError::TooDeep(20));
+
+        // 20段までなら下限の範囲に収まる
+        let mut root = Node::new(0);
+        let mut cur = &mut root;
+        for i in 1..20 {
+            cur = cur.create_right_node(i).unwrap();
+        }
+        let array = root.to_array_layout().unwrap();
+        assert_eq!(array.len(), (1 << 20) - 1);
+        assert_eq!(array.iter().flatten().count(), 20);
+    }
+}
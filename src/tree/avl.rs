@@ -0,0 +1,353 @@
+use std::cmp::{self, Ordering};
+use std::mem;
+
+use super::Node;
+use super::ordered::{self, Entry, InvariantError, Link, MapIter, OrderedMap};
+
+type AvlLink<K, V> = Link<K, V, usize>;
+type AvlNode<K, V> = Node<Entry<K, V, usize>>;
+
+// AVL木　各ノードの左右の部分木の高さの差が1以下になるように回転して高さをO(log n)に保つ
+// Entryのmetaにはそのノードを根とする部分木の高さ(葉は1)を入れる
+#[derive(Debug)]
+pub struct AvlTree<K, V> {
+    root: AvlLink<K, V>,
+    len: usize,
+}
+
+impl<K: Ord, V> AvlTree<K, V> {
+    pub fn new() -> Self {
+        AvlTree {
+            root: None,
+            len: 0,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.root = None;
+        self.len = 0;
+    }
+
+    pub fn height(&self) -> usize {
+        height_of(&self.root)
+    }
+
+    // 既にキーがある場合は値を置き換え、古い値を返す
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let ret = insert_at(&mut self.root, key, value);
+        if ret.is_none() {
+            self.len += 1;
+        }
+        ret
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        ordered::get_mut(&mut self.root, key)
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let ret = remove_at(&mut self.root, key);
+        if ret.is_some() {
+            self.len -= 1;
+        }
+        ret
+    }
+
+    // キーの順序、要素数、部分木の要素数、各ノードの高さ、左右の高さの差をすべて確かめる
+    pub fn check_invariants(&self) -> Result<(), InvariantError> {
+        ordered::check_order(self.root(), self.len)?;
+        check_node(self.root())?;
+        ordered::check_size(self.root())
+    }
+}
+
+// AVLで平衡を保つ木のノードの付加情報　高さのほかに、子から計算できる値を持たせられる
+// 回転や挿入・削除で子が変わったノードでは、高さを入れ直した後にrecalcが呼ばれる
+pub(super) trait AvlMeta<K, V>: Sized {
+    fn leaf(key: &K) -> Self;
+    fn height(&self) -> usize;
+    fn set_height(&mut self, height: usize);
+
+    // 高さ以外の付加情報を、正しいことが分かっている子から計算し直す
+    fn recalc(_node: &mut Node<Entry<K, V, Self>>) {}
+
+    // 高さ以外の付加情報が正しいか確かめる
+    fn check(_node: &Node<Entry<K, V, Self>>) -> Result<(), InvariantError> {
+        Ok(())
+    }
+}
+
+// AvlTreeのmetaは高さだけ
+impl<K, V> AvlMeta<K, V> for usize {
+    fn leaf(_key: &K) -> Self {
+        1
+    }
+
+    fn height(&self) -> usize {
+        *self
+    }
+
+    fn set_height(&mut self, height: usize) {
+        *self = height;
+    }
+}
+
+// 各ノードの高さ、左右の高さの差、その他の付加情報を確かめ、部分木の高さを返す
+pub(super) fn check_node<K, V, M: AvlMeta<K, V>>(node: Option<&Node<Entry<K, V, M>>>) -> Result<usize, InvariantError> {
+    match node {
+        None => Ok(0),
+        Some(n) => {
+            let left = check_node(n.left())?;
+            let right = check_node(n.right())?;
+
+            if n.data.meta.height() != 1 + cmp::max(left, right) {
+                return Err( InvariantError::Height );
+            }
+            if left.abs_diff(right) > 1 {
+                return Err( InvariantError::Balance );
+            }
+            M::check(n)?;
+            Ok(n.data.meta.height())
+        }
+    }
+}
+
+pub(super) fn insert_at<K: Ord, V, M: AvlMeta<K, V>>(link: &mut Link<K, V, M>, key: K, value: V) -> Option<V> {
+    let node = match link {
+        Some(n) => n,
+        None => {
+            let meta = M::leaf(&key);
+            *link = Some(Box::new(Node::new(Entry::new(key, value, meta))));
+            return None;
+        }
+    };
+
+    let ret = match key.cmp(&node.data.key) {
+        Ordering::Less => insert_at(&mut node.left, key, value),
+        Ordering::Greater => insert_at(&mut node.right, key, value),
+        Ordering::Equal => return Some(mem::replace(&mut node.data.value, value)),
+    };
+
+    rebalance(node);
+    ret
+}
+
+pub(super) fn remove_at<K: Ord, V, M: AvlMeta<K, V>>(link: &mut Link<K, V, M>, key: &K) -> Option<V> {
+    let node = link.as_mut()?;
+
+    let ret = match key.cmp(&node.data.key) {
+        Ordering::Less => remove_at(&mut node.left, key),
+        Ordering::Greater => remove_at(&mut node.right, key),
+        Ordering::Equal => {
+            let mut node = link.take().unwrap();
+
+            *link = match (node.left.take(), node.right.take()) {
+                (None, None) => None,
+                (Some(l), None) => Some(l),
+                (None, Some(r)) => Some(r),
+                (Some(l), Some(r)) => {
+                    // 子が2つある場合は、右の部分木の最小ノードを持ってきて置き換える
+                    let mut right = Some(r);
+                    let mut successor = take_min(&mut right);
+                    *successor.left = Some(l);
+                    *successor.right = right;
+                    rebalance(&mut successor);
+                    Some(successor)
+                }
+            };
+            return Some(node.into_data().value);
+        }
+    };
+
+    if let Some(n) = link.as_mut() {
+        rebalance(n);
+    }
+    ret
+}
+
+// 部分木から最小ノードを切り離す　通ってきたノードは戻りながら回転し直す
+fn take_min<K, V, M: AvlMeta<K, V>>(link: &mut Link<K, V, M>) -> Box<Node<Entry<K, V, M>>> {
+    let node = link.as_mut().unwrap();
+
+    if node.left.is_some() {
+        let min = take_min(&mut node.left);
+        rebalance(node);
+        min
+    } else {
+        let mut min = link.take().unwrap();
+        *link = min.right.take();
+        min
+    }
+}
+
+pub(super) fn height_of<K, V, M: AvlMeta<K, V>>(link: &Link<K, V, M>) -> usize {
+    match link {
+        Some(n) => n.data.meta.height(),
+        None => 0,
+    }
+}
+
+// 高さ、その他の付加情報、要素数を子から計算し直す
+fn update<K, V, M: AvlMeta<K, V>>(node: &mut Node<Entry<K, V, M>>) {
+    let height = 1 + cmp::max(height_of(&node.left), height_of(&node.right));
+    node.data.meta.set_height(height);
+    M::recalc(node);
+    ordered::update_size(node);
+}
+
+// 左が高ければ正、右が高ければ負
+fn balance_factor<K, V, M: AvlMeta<K, V>>(node: &Node<Entry<K, V, M>>) -> isize {
+    height_of(&node.left) as isize - height_of(&node.right) as isize
+}
+
+// 子の付加情報と要素数が正しいことを前提に、nodeのそれらを更新して必要なら回転する
+// 回転で子が変わったノードは下から順に更新し直す
+fn rebalance<K, V, M: AvlMeta<K, V>>(node: &mut Node<Entry<K, V, M>>) {
+    update(node);
+
+    let bf = balance_factor(node);
+
+    if bf > 1 {
+        let left = node.left_mut().unwrap();
+        if balance_factor(left) < 0 {    // 左の子の右側が高い場合は、先に左の子を左回転する
+            left.rotate_left().unwrap();
+            update(left.left_mut().unwrap());
+            update(left);
+        }
+        node.rotate_right().unwrap();
+        update(node.right_mut().unwrap());
+        update(node);
+    } else if bf < -1 {
+        let right = node.right_mut().unwrap();
+        if balance_factor(right) > 0 {
+            right.rotate_right().unwrap();
+            update(right.right_mut().unwrap());
+            update(right);
+        }
+        node.rotate_left().unwrap();
+        update(node.left_mut().unwrap());
+        update(node);
+    }
+}
+
+impl<K: Ord, V> Default for AvlTree<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord, V> OrderedMap<K, V> for AvlTree<K, V> {
+    type Meta = usize;
+
+    fn root(&self) -> Option<&AvlNode<K, V>> {
+        self.root.as_deref()
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+ordered::impl_map_into_iter!(AvlTree, usize);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::SearchOrder;
+    use std::collections::BTreeMap;
+    use crate::tree::test_util::{self, lcg};
+
+    fn preorder_keys(tree: &AvlTree<i32, ()>) -> Vec<i32> {
+        tree.root().unwrap().iter(&SearchOrder::PreOrder).map(|e| e.key).collect()
+    }
+
+    #[test]
+    fn rotation_cases() {
+        // LL, RR, LR, RLの4パターン　どれも中央の値が根になる
+        for keys in &[[3, 2, 1], [1, 2, 3], [3, 1, 2], [1, 3, 2]] {
+            let mut tree = AvlTree::new();
+            for k in keys.iter() {
+                tree.insert(*k, ());
+                tree.check_invariants().unwrap();
+            }
+            assert_eq!(preorder_keys(&tree), vec![2, 1, 3]);
+            assert_eq!(tree.height(), 2);
+        }
+    }
+
+    #[test]
+    fn sorted_insert() {
+        let mut tree = AvlTree::new();
+
+        for k in 0..1023 {
+            tree.insert(k, k * 2);
+        }
+        tree.check_invariants().unwrap();
+
+        // 2^10 - 1 個を昇順に入れると完全二分木になる
+        assert_eq!(tree.height(), 10);
+        assert_eq!(tree.len(), 1023);
+        assert_eq!(tree.get(&500), Some(&1000));
+        assert_eq!(tree.min(), Some((&0, &0)));
+        assert_eq!(tree.max(), Some((&1022, &2044)));
+
+        let keys: Vec<i32> = tree.range(10..15).map(|(k, _)| *k).collect();
+        assert_eq!(keys, vec![10, 11, 12, 13, 14]);
+    }
+
+    #[test]
+    fn remove() {
+        let mut tree = AvlTree::new();
+
+        for k in 0..100 {
+            tree.insert(k, ());
+        }
+        for k in (0..100).filter(|k| k % 3 != 1) {
+            assert_eq!(tree.remove(&k), Some(()));
+            tree.check_invariants().unwrap();
+        }
+        assert_eq!(tree.remove(&0), None);
+        assert_eq!(tree.len(), 33);
+
+        let keys: Vec<i32> = tree.iter().map(|(k, _)| *k).collect();
+        assert_eq!(keys, (0..100).filter(|k| k % 3 == 1).collect::<Vec<i32>>());
+        assert!(tree.height() <= 7);
+    }
+
+    #[test]
+    fn compare_with_btreemap() {
+        test_util::compare_with_btreemap(&mut AvlTree::new(), 7);
+    }
+
+    #[test]
+    fn order_statistics() {
+        let mut seed = 3;
+        let mut tree = AvlTree::new();
+        let mut expected = BTreeMap::new();
+
+        for i in 0..1000 {
+            let key = lcg(&mut seed) % 400;
+
+            if lcg(&mut seed) % 3 == 1 {
+                tree.remove(&key);
+                expected.remove(&key);
+            } else {
+                tree.insert(key, i);
+                expected.insert(key, i);
+            }
+        }
+        tree.check_invariants().unwrap();
+
+        for (k, e) in expected.iter().enumerate() {
+            assert_eq!(tree.select(k), Some(e));
+            assert_eq!(tree.rank(e.0), k);
+        }
+        assert_eq!(tree.select(expected.len()), None);
+        assert_eq!(tree.rank(&400), expected.len());
+
+        let n = expected.len();
+        assert_eq!(tree.median(), expected.iter().nth(n.div_ceil(2) - 1));
+        assert_eq!(tree.percentile(0.0), expected.iter().next());
+        assert_eq!(tree.percentile(100.0), expected.iter().next_back());
+    }
+}
@@ -0,0 +1,262 @@
+use std::cmp::Ordering;
+use std::mem;
+
+use super::Node;
+use super::ordered::{self, Entry, InvariantError, Link, MapIter, OrderedMap};
+
+// Nodeの上に作った二分探索木　左の子孫 < ノード < 右の子孫 になるようにキーを並べる
+#[derive(Debug)]
+pub struct BstMap<K, V> {
+    root: Link<K, V, ()>,
+    len: usize,
+}
+
+impl<K: Ord, V> BstMap<K, V> {
+    pub fn new() -> Self {
+        BstMap {
+            root: None,
+            len: 0,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.root = None;
+        self.len = 0;
+    }
+
+    // 既にキーがある場合は値を置き換え、古い値を返す
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        if let Some(node) = ordered::find_link(&mut self.root, &key) {
+            return Some(mem::replace(&mut node.data.value, value));
+        }
+
+        // 新しいキーなので、通り道のノードの要素数を1つずつ増やしながら降りる
+        let mut link = &mut self.root;
+        while let Some(go_left) = link.as_ref().map(|n| key < n.data.key) {
+            let node = link.as_mut().unwrap();
+            node.data.size += 1;
+            link = if go_left { &mut node.left } else { &mut node.right };
+        }
+
+        *link = Some(Box::new(Node::new(Entry::new(key, value, ()))));
+        self.len += 1;
+        None
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        ordered::get_mut(&mut self.root, key)
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        if !self.contains_key(key) {
+            return None;
+        }
+
+        // 通り道のノードの要素数を1つずつ減らしながら降りる
+        let mut link = &mut self.root;
+        while let Some(ord) = link.as_ref().map(|n| key.cmp(&n.data.key)) {
+            if ord == Ordering::Equal {
+                break;
+            }
+            let node = link.as_mut().unwrap();
+            node.data.size -= 1;
+            link = if ord == Ordering::Less { &mut node.left } else { &mut node.right };
+        }
+        let mut node = link.take().unwrap();
+
+        *link = match (node.left.take(), node.right.take()) {
+            (None, None) => None,
+            (Some(l), None) => Some(l),
+            (None, Some(r)) => Some(r),
+            (Some(l), Some(r)) => {
+                // 子が2つある場合は、右の部分木の最小ノードを持ってきて置き換える
+                let mut right = Some(r);
+                let mut successor = Self::take_min(&mut right);
+                *successor.left = Some(l);
+                *successor.right = right;
+                ordered::update_size(&mut successor);
+                Some(successor)
+            }
+        };
+
+        self.len -= 1;
+        Some(node.into_data().value)
+    }
+
+    pub fn check_invariants(&self) -> Result<(), InvariantError> {
+        ordered::check_order(self.root(), self.len)?;
+        ordered::check_size(self.root())
+    }
+
+    // 部分木から最小ノードを切り離す　最小ノードの右の子はその位置に繰り上げる
+    fn take_min(mut link: &mut Link<K, V, ()>) -> Box<Node<Entry<K, V>>> {
+        while link.as_ref().unwrap().left.is_some() {
+            let node = link.as_mut().unwrap();
+            node.data.size -= 1;
+            link = &mut node.left;
+        }
+
+        let mut node = link.take().unwrap();
+        *link = node.right.take();
+        node
+    }
+}
+
+impl<K: Ord, V> Default for BstMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord, V> OrderedMap<K, V> for BstMap<K, V> {
+    type Meta = ();
+
+    fn root(&self) -> Option<&Node<Entry<K, V>>> {
+        self.root.as_deref()
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+ordered::impl_map_into_iter!(BstMap, ());
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ops::Bound;
+    use crate::tree::test_util;
+
+    fn create_test_map() -> BstMap<i32, String> {
+        let mut map = BstMap::new();
+        for k in &[50, 30, 70, 20, 40, 60, 80, 35, 45, 65] {
+            map.insert(*k, k.to_string());
+        }
+        map
+    }
+
+    #[test]
+    fn insert_get() {
+        let mut map = create_test_map();
+
+        assert_eq!(map.len(), 10);
+        assert_eq!(map.get(&40), Some(&"40".to_string()));
+        assert_eq!(map.get(&41), None);
+        assert!(map.contains_key(&65));
+
+        assert_eq!(map.insert(40, "forty".to_string()), Some("40".to_string()));
+        assert_eq!(map.len(), 10);
+        assert_eq!(map.get(&40), Some(&"forty".to_string()));
+
+        map.get_mut(&65).unwrap().push('!');
+        assert_eq!(map.get(&65), Some(&"65!".to_string()));
+
+        // 根は最初に挿入したキーのまま
+        assert_eq!(map.root().unwrap().as_ref().key, 50);
+    }
+
+    #[test]
+    fn remove() {
+        let mut map = create_test_map();
+
+        assert_eq!(map.remove(&35), Some("35".to_string()));    // 葉
+        assert_eq!(map.remove(&60), Some("60".to_string()));    // 子が1つ
+        assert_eq!(map.remove(&30), Some("30".to_string()));    // 子が2つ
+        assert_eq!(map.remove(&50), Some("50".to_string()));    // 根
+        assert_eq!(map.remove(&50), None);
+        assert_eq!(map.len(), 6);
+
+        let keys: Vec<i32> = map.iter().map(|(k, _)| *k).collect();
+        assert_eq!(keys, vec![20, 40, 45, 65, 70, 80]);
+
+        for k in &keys {
+            assert!(map.remove(k).is_some());
+        }
+        assert!(map.is_empty());
+        assert!(map.root().is_none());
+    }
+
+    #[test]
+    fn min_max() {
+        let mut map = create_test_map();
+
+        assert_eq!(map.min(), Some((&20, &"20".to_string())));
+        assert_eq!(map.max(), Some((&80, &"80".to_string())));
+
+        map.clear();
+        assert_eq!(map.min(), None);
+        assert_eq!(map.max(), None);
+    }
+
+    #[test]
+    fn order_statistics() {
+        let mut map = create_test_map();
+
+        // キーは 20 30 35 40 45 50 60 65 70 80
+        assert_eq!(map.select(0), Some((&20, &"20".to_string())));
+        assert_eq!(map.select(9), Some((&80, &"80".to_string())));
+        assert_eq!(map.select(10), None);
+        assert_eq!(map.rank(&10), 0);
+        assert_eq!(map.rank(&45), 4);
+        assert_eq!(map.rank(&46), 5);
+        assert_eq!(map.rank(&100), 10);
+
+        // 要素数が偶数のときは小さい方の中央値
+        assert_eq!(map.median().map(|(k, _)| *k), Some(45));
+        assert_eq!(map.percentile(0.0).map(|(k, _)| *k), Some(20));
+        assert_eq!(map.percentile(90.0).map(|(k, _)| *k), Some(70));
+        assert_eq!(map.percentile(100.0).map(|(k, _)| *k), Some(80));
+        assert_eq!(map.percentile(-1.0), None);
+        assert_eq!(map.percentile(100.5), None);
+        assert_eq!(map.percentile(f64::NAN), None);
+
+        map.remove(&45);
+        assert_eq!(map.median().map(|(k, _)| *k), Some(50));
+        assert_eq!(map.rank(&45), 4);
+        map.check_invariants().unwrap();
+
+        map.clear();
+        assert_eq!(map.select(0), None);
+        assert_eq!(map.median(), None);
+        assert_eq!(map.percentile(50.0), None);
+    }
+
+    #[test]
+    fn iter() {
+        let map = create_test_map();
+
+        let keys: Vec<i32> = map.iter().map(|(k, _)| *k).collect();
+        assert_eq!(keys, vec![20, 30, 35, 40, 45, 50, 60, 65, 70, 80]);
+
+        let keys: Vec<i32> = map.iter().rev().take(3).map(|(k, _)| *k).collect();
+        assert_eq!(keys, vec![80, 70, 65]);
+
+        assert_eq!((&map).into_iter().len(), 10);
+        let mut iter = map.iter();
+        iter.next();
+        iter.next_back();
+        assert_eq!(iter.len(), 8);
+        assert_eq!(BstMap::<i32, i32>::new().iter().next(), None);
+    }
+
+    #[test]
+    fn range() {
+        let map = create_test_map();
+        let keys = |r: Vec<(&i32, &String)>| r.into_iter().map(|(k, _)| *k).collect::<Vec<i32>>();
+
+        assert_eq!(keys(map.range(35..60).collect()), vec![35, 40, 45, 50]);
+        assert_eq!(keys(map.range(35..=60).collect()), vec![35, 40, 45, 50, 60]);
+        assert_eq!(keys(map.range(36..).collect()), vec![40, 45, 50, 60, 65, 70, 80]);
+        assert_eq!(keys(map.range(..30).collect()), vec![20]);
+        assert_eq!(keys(map.range(..).collect()), vec![20, 30, 35, 40, 45, 50, 60, 65, 70, 80]);
+        assert_eq!(keys(map.range((Bound::Excluded(45), Bound::Excluded(65))).collect()), vec![50, 60]);
+        assert!(map.range(81..).next().is_none());
+        assert!(map.range(41..45).next().is_none());
+    }
+
+    #[test]
+    fn compare_with_btreemap() {
+        test_util::compare_with_btreemap(&mut BstMap::new(), 1);
+    }
+}
@@ -0,0 +1,230 @@
+use std::convert::Infallible;
+
+use super::{Node, NodeError};
+
+// どの関数も帰りがけ順(左→右→ノード)にコールバックを呼ぶ
+// 再帰を使わないので、深い木でもスタックが溢れない
+impl<T> Node<T> {
+    // 木の形はそのままに、要素を変換した木を作る
+    pub fn map<U, F>(self, mut f: F) -> Node<U>
+    where F: FnMut(T) -> U {
+        enum Frame<T> {
+            Visit(Node<T>),
+            Build(Node<T>, bool, bool),   // 子を外したノード, 左の子があったか, 右の子があったか
+        }
+
+        let mut stack = vec![Frame::Visit(self)];
+        let mut done: Vec<Node<U>> = Vec::new();
+
+        while let Some(frame) = stack.pop() {
+            match frame {
+                Frame::Visit(mut node) => {
+                    let left = node.take_left();
+                    let right = node.take_right();
+
+                    stack.push(Frame::Build(node, left.is_some(), right.is_some()));
+                    stack.extend(right.map(Frame::Visit));
+                    stack.extend(left.map(Frame::Visit));
+                },
+                Frame::Build(node, has_left, has_right) => {
+                    let right = if has_right { done.pop() } else { None };
+                    let left = if has_left { done.pop() } else { None };
+
+                    done.push(Node {
+                        data: f(node.into_data()),
+                        left: left.map(Box::new).into(),
+                        right: right.map(Box::new).into(),
+                    });
+                }
+            }
+        }
+
+        done.pop().unwrap()
+    }
+
+    pub fn map_ref<U, F>(&self, mut f: F) -> Node<U>
+    where F: FnMut(&T) -> U {
+        self.fold(|x, left, right| Node {
+            data: f(x),
+            left: left.map(Box::new).into(),
+            right: right.map(Box::new).into(),
+        })
+    }
+
+    // 下から上へ畳み込む　各ノードでは、左右の子の結果(子がなければNone)を受け取る
+    pub fn fold<U, F>(&self, mut f: F) -> U
+    where F: FnMut(&T, Option<U>, Option<U>) -> U {
+        match self.try_fold(|x, left, right| Ok::<U, Infallible>(f(x, left, right))) {
+            Ok(u) => u,
+            Err(e) => match e {},
+        }
+    }
+
+    // foldと同じだが、コールバックがErrを返したらそこで止める
+    pub fn try_fold<U, E, F>(&self, mut f: F) -> Result<U, E>
+    where F: FnMut(&T, Option<U>, Option<U>) -> Result<U, E> {
+        let mut stack = vec![(self, false)];
+        let mut done: Vec<U> = Vec::new();
+
+        while let Some((node, expanded)) = stack.pop() {
+            if !expanded {
+                stack.push((node, true));
+                stack.extend(node.right().map(|r| (r, false)));
+                stack.extend(node.left().map(|l| (l, false)));
+                continue;
+            }
+
+            let right = if node.right.is_some() { done.pop() } else { None };
+            let left = if node.left.is_some() { done.pop() } else { None };
+
+            done.push(f(&node.data, left, right)?);
+        }
+
+        Ok(done.pop().unwrap())
+    }
+
+    // 同じ形の2つの木の、同じ位置の要素を組み合わせた木を作る
+    pub fn zip_with<U, V, F>(&self, other: &Node<U>, mut f: F) -> Result<Node<V>, NodeError>
+    where F: FnMut(&T, &U) -> V {
+        let mut stack = vec![(self, other, false)];
+        let mut done: Vec<Node<V>> = Vec::new();
+
+        while let Some((a, b, expanded)) = stack.pop() {
+            if !expanded {
+                if a.left.is_some() != b.left.is_some() || a.right.is_some() != b.right.is_some() {
+                    return Err( NodeError::ShapeError );
+                }
+
+                stack.push((a, b, true));
+                if let (Some(ar), Some(br)) = (a.right(), b.right()) {
+                    stack.push((ar, br, false));
+                }
+                if let (Some(al), Some(bl)) = (a.left(), b.left()) {
+                    stack.push((al, bl, false));
+                }
+                continue;
+            }
+
+            let right = if a.right.is_some() { done.pop() } else { None };
+            let left = if a.left.is_some() { done.pop() } else { None };
+
+            done.push(Node {
+                data: f(&a.data, &b.data),
+                left: left.map(Box::new).into(),
+                right: right.map(Box::new).into(),
+            });
+        }
+
+        Ok(done.pop().unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::SearchOrder;
+    use crate::tree::test_util::create_test_tree;
+
+    // 1 + 2 * 3
+    fn create_formula_tree() -> Node<&'static str> {
+        let mut root = Node::new("+");
+        root.create_left_node("1").unwrap();
+        let mul = root.create_right_node("*").unwrap();
+        mul.create_left_node("2").unwrap();
+        mul.create_right_node("3").unwrap();
+        root
+    }
+
+    #[test]
+    fn map() {
+        let mut calls = Vec::new();
+        let mapped = create_test_tree().map(|x| {
+            calls.push(x);
+            x.to_string() + "!"
+        });
+
+        assert_eq!(calls, vec![4, 5, 2, 6, 7, 3, 1]);
+        let result: Vec<&str> = mapped.iter(&SearchOrder::PreOrder).map(|s| s.as_str()).collect();
+        assert_eq!(result, vec!["1!", "2!", "4!", "5!", "3!", "6!", "7!"]);
+
+        let root = create_test_tree();
+        let doubled = root.map_ref(|x| x * 2);
+        let result: Vec<i32> = doubled.iter(&SearchOrder::InOrder).cloned().collect();
+        assert_eq!(result, vec![8, 4, 10, 2, 12, 6, 14]);
+    }
+
+    #[test]
+    fn fold() {
+        let root = create_test_tree();
+
+        let sum = root.fold(|x, l, r| x + l.unwrap_or(0) + r.unwrap_or(0));
+        assert_eq!(sum, 28);
+
+        let height = root.fold(|_, l: Option<usize>, r: Option<usize>| 1 + l.unwrap_or(0).max(r.unwrap_or(0)));
+        assert_eq!(height, 3);
+
+        // 数式の木を評価する
+        let value = create_formula_tree().fold(|x, l: Option<f64>, r: Option<f64>| {
+            match *x {
+                "+" => l.unwrap() + r.unwrap(),
+                "*" => l.unwrap() * r.unwrap(),
+                n => n.parse().unwrap(),
+            }
+        });
+        assert_eq!(value, 7.0);
+    }
+
+    #[test]
+    fn try_fold() {
+        let eval = |x: &&str, l: Option<f64>, r: Option<f64>| -> Result<f64, String> {
+            match *x {
+                "+" => Ok(l.unwrap() + r.unwrap()),
+                "*" => Ok(l.unwrap() * r.unwrap()),
+                n => n.parse().map_err(|_| format!("not a number: {}", n)),
+            }
+        };
+
+        assert_eq!(create_formula_tree().try_fold(eval), Ok(7.0));
+
+        let mut tree = create_formula_tree();
+        *tree.right_mut().unwrap().left_mut().unwrap().as_mut() = "x";
+
+        let mut visited = 0;
+        let result = tree.try_fold(|x, l, r| { visited += 1; eval(x, l, r) });
+        assert_eq!(result, Err("not a number: x".to_string()));
+        assert_eq!(visited, 2);     // 1, x まで見たところで止まる
+    }
+
+    #[test]
+    fn zip_with() {
+        let a = create_test_tree();
+        let b = a.map_ref(|x| x * 10);
+
+        let zipped = a.zip_with(&b, |x, y| x + y).unwrap();
+        let result: Vec<i32> = zipped.iter(&SearchOrder::PreOrder).cloned().collect();
+        assert_eq!(result, vec![11, 22, 44, 55, 33, 66, 77]);
+
+        let mut c = create_test_tree();
+        c.right_mut().unwrap().take_right();
+        assert_eq!(a.zip_with(&c, |x, y| x + y).unwrap_err(), NodeError::ShapeError);
+    }
+
+    #[test]
+    fn deep_tree() {
+        let mut root = Node::new(0);
+        let mut cur = &mut root;
+        for i in 1..200_000 {
+            cur = cur.create_right_node(i).unwrap();
+        }
+
+        let depth = root.fold(|_, l: Option<usize>, r: Option<usize>| 1 + l.unwrap_or(0).max(r.unwrap_or(0)));
+        assert_eq!(depth, 200_000);
+
+        let mapped = root.map_ref(|x| *x as i64);
+        let zipped = mapped.zip_with(&root, |a, b| a - *b as i64).unwrap();
+        assert!(zipped.iter(&SearchOrder::PreOrder).all(|x| *x == 0));
+
+        let mapped = root.map(|x| x + 1);
+        assert_eq!(mapped.iter(&SearchOrder::PostOrder).next(), Some(&200_000));
+    }
+}
@@ -0,0 +1,219 @@
+use super::{Node, NodeError, NodePath, Side};
+
+// 親ノードは、注目しているノードの側の子を外した状態で持っておく
+#[derive(Debug)]
+struct Crumb<T> {
+    side: Side,
+    parent: Node<T>,
+}
+
+// ジッパー　木の中の1つのノードに注目して、左右の子や親へ移動しながら部分木を編集する
+// 注目しているノードを根から切り離して持ち、根までの経路をpathに積んでおく
+#[derive(Debug)]
+pub struct Cursor<T> {
+    focus: Node<T>,
+    path: Vec<Crumb<T>>,
+}
+
+impl<T> Cursor<T> {
+    pub fn new(root: Node<T>) -> Self {
+        Cursor {
+            focus: root,
+            path: Vec::new(),
+        }
+    }
+
+    pub fn focus(&self) -> &Node<T> {
+        &self.focus
+    }
+
+    pub fn focus_mut(&mut self) -> &mut Node<T> {
+        &mut self.focus
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.path.is_empty()
+    }
+
+    // 根の深さは0
+    pub fn depth(&self) -> usize {
+        self.path.len()
+    }
+
+    // 注目しているノードが親のどちら側の子か　根ならNone
+    pub fn side(&self) -> Option<Side> {
+        self.path.last().map(|c| c.side)
+    }
+
+    // 根から注目しているノードまでの経路
+    pub fn path(&self) -> NodePath {
+        self.path.iter().map(|c| c.side).collect()
+    }
+
+    pub fn parent(&self) -> Option<&T> {
+        self.path.last().map(|c| c.parent.as_ref())
+    }
+
+    pub fn sibling(&self) -> Option<&Node<T>> {
+        let crumb = self.path.last()?;
+
+        match crumb.side {
+            Side::Left => crumb.parent.right(),
+            Side::Right => crumb.parent.left(),
+        }
+    }
+
+    // 親から根に向かって順に返す
+    pub fn ancestors(&self) -> impl Iterator<Item = &T> {
+        self.path.iter().rev().map(|c| c.parent.as_ref())
+    }
+
+    pub fn to_left(&mut self) -> Result<(), NodeError> {
+        self.to_child(Side::Left)
+    }
+
+    pub fn to_right(&mut self) -> Result<(), NodeError> {
+        self.to_child(Side::Right)
+    }
+
+    pub fn to_child(&mut self, side: Side) -> Result<(), NodeError> {
+        let child = match side {
+            Side::Left => self.focus.take_left(),
+            Side::Right => self.focus.take_right(),
+        };
+
+        match child {
+            Some(c) => {
+                let parent = std::mem::replace(&mut self.focus, c);
+                self.path.push(Crumb { side, parent });
+                Ok(())
+            },
+            None => Err( NodeError::NoNode ),
+        }
+    }
+
+    pub fn to_parent(&mut self) -> Result<(), NodeError> {
+        let crumb = match self.path.pop() {
+            Some(c) => c,
+            None => return Err( NodeError::NoNode ),
+        };
+
+        let child = std::mem::replace(&mut self.focus, crumb.parent);
+        match crumb.side {
+            Side::Left => self.focus.replace_left(child),
+            Side::Right => self.focus.replace_right(child),
+        }
+        Ok(())
+    }
+
+    pub fn to_root(&mut self) {
+        while self.to_parent().is_ok() {}
+    }
+
+    // 注目している部分木を入れ替え、元の部分木を返す
+    pub fn replace(&mut self, tree: Node<T>) -> Node<T> {
+        std::mem::replace(&mut self.focus, tree)
+    }
+
+    pub fn insert_left(&mut self, tree: Node<T>) -> Result<(), NodeError> {
+        self.focus.add_node_left(tree)?;
+        Ok(())
+    }
+
+    pub fn insert_right(&mut self, tree: Node<T>) -> Result<(), NodeError> {
+        self.focus.add_node_right(tree)?;
+        Ok(())
+    }
+
+    // 注目している部分木を切り離して返し、親に移動する　根は切り離せない
+    pub fn detach(&mut self) -> Result<Node<T>, NodeError> {
+        let crumb = match self.path.pop() {
+            Some(c) => c,
+            None => return Err( NodeError::NoNode ),
+        };
+
+        Ok(std::mem::replace(&mut self.focus, crumb.parent))
+    }
+
+    // 根まで戻って木を組み立て直す
+    pub fn into_root(mut self) -> Node<T> {
+        self.to_root();
+        self.focus
+    }
+}
+
+impl<T> From<Node<T>> for Cursor<T> {
+    fn from(root: Node<T>) -> Self {
+        Cursor::new(root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::SearchOrder;
+    use crate::tree::test_util::create_test_tree;
+
+    fn preorder(root: &Node<i32>) -> Vec<i32> {
+        root.iter(&SearchOrder::PreOrder).cloned().collect()
+    }
+
+    #[test]
+    fn navigate() {
+        let mut cursor = Cursor::new(create_test_tree());
+
+        assert!(cursor.is_root());
+        assert_eq!(cursor.parent(), None);
+        assert_eq!(cursor.to_parent(), Err(NodeError::NoNode));
+
+        cursor.to_left().unwrap();
+        cursor.to_right().unwrap();
+        assert_eq!(cursor.focus().as_ref(), &5);
+        assert_eq!(cursor.depth(), 2);
+        assert_eq!(cursor.side(), Some(Side::Right));
+        assert_eq!(cursor.path(), NodePath::from(vec![Side::Left, Side::Right]));
+        assert_eq!(cursor.parent(), Some(&2));
+        assert_eq!(cursor.sibling().unwrap().as_ref(), &4);
+        assert_eq!(cursor.ancestors().cloned().collect::<Vec<i32>>(), vec![2, 1]);
+        assert_eq!(cursor.to_left(), Err(NodeError::NoNode));
+
+        cursor.to_parent().unwrap();
+        cursor.to_parent().unwrap();
+        cursor.to_right().unwrap();
+        assert_eq!(cursor.focus().as_ref(), &3);
+        assert_eq!(cursor.sibling().unwrap().as_ref(), &2);
+
+        // 移動しただけなら木の形は変わらない
+        assert_eq!(preorder(&cursor.into_root()), vec![1, 2, 4, 5, 3, 6, 7]);
+    }
+
+    #[test]
+    fn edit() {
+        let mut cursor = Cursor::from(create_test_tree());
+
+        cursor.to_left().unwrap();
+        let old = cursor.replace(Node::new(8));
+        assert_eq!(preorder(&old), vec![2, 4, 5]);
+
+        cursor.insert_left(Node::new(9)).unwrap();
+        assert_eq!(cursor.insert_left(Node::new(10)), Err(NodeError::ChildAddError));
+        *cursor.focus_mut().as_mut() += 10;
+
+        cursor.to_root();
+        cursor.to_right().unwrap();
+        cursor.to_left().unwrap();
+
+        let detached = cursor.detach().unwrap();
+        assert_eq!(preorder(&detached), vec![6]);
+        assert_eq!(cursor.focus().as_ref(), &3);
+
+        cursor.insert_right(Node::new(11)).unwrap_err();
+        cursor.insert_left(detached).unwrap();
+
+        let root = cursor.into_root();
+        assert_eq!(preorder(&root), vec![1, 18, 9, 3, 6, 7]);
+
+        let mut cursor = Cursor::new(root);
+        assert_eq!(cursor.detach().unwrap_err(), NodeError::NoNode);
+    }
+}
@@ -0,0 +1,225 @@
+use std::fmt;
+
+use super::{Node, NodePath, Side};
+
+// 2つの木の違い　pathは根からの経路(Movedでは移動先)
+// Inserted/Deletedのkeptは、部分木がノードを1つ挟んで上下に移っただけのときに、
+// そのノードのどちら側の子が元からある部分木かを表す　その子の中身は変わっていない
+#[derive(Debug, Clone, PartialEq)]
+pub enum TreeChange<'r, T> {
+    Changed { path: NodePath, old: &'r T, new: &'r T },                         // 両方にあるノードの要素が違う
+    Inserted { path: NodePath, subtree: &'r Node<T>, kept: Option<Side> },      // 新しい木にだけある部分木
+    Deleted { path: NodePath, subtree: &'r Node<T>, kept: Option<Side> },       // 古い木にだけある部分木
+    Moved { from: NodePath, path: NodePath, subtree: &'r Node<T> },             // 同じ部分木が親の反対側の子に移った
+}
+
+impl<'r, T> TreeChange<'r, T> {
+    pub fn path(&self) -> &NodePath {
+        match self {
+            TreeChange::Changed { path, .. } => path,
+            TreeChange::Inserted { path, .. } => path,
+            TreeChange::Deleted { path, .. } => path,
+            TreeChange::Moved { path, .. } => path,
+        }
+    }
+}
+
+impl<'r, T: fmt::Debug> fmt::Display for TreeChange<'r, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TreeChange::Changed { path, old, new } => write!(f, "changed  {}: {:?} -> {:?}", path, old, new),
+            TreeChange::Inserted { path, subtree, kept } => {
+                write!(f, "inserted {}: {:?} ({} nodes", path, subtree.data, count_new(subtree, *kept))?;
+                match kept {
+                    Some(side) => write!(f, ", old {} moved to {})", path, path.join(*side)),
+                    None => write!(f, ")"),
+                }
+            },
+            TreeChange::Deleted { path, subtree, kept } => {
+                write!(f, "deleted  {}: {:?} ({} nodes", path, subtree.data, count_new(subtree, *kept))?;
+                match kept {
+                    Some(side) => write!(f, ", old {} moved to {})", path.join(*side), path),
+                    None => write!(f, ")"),
+                }
+            },
+            TreeChange::Moved { from, path, subtree } => write!(f, "moved    {} -> {}: {:?} ({} nodes)", from, path, subtree.data, subtree.len()),
+        }
+    }
+}
+
+// keptの子を除いた、実際に増えた(減った)ノードの数
+fn count_new<T>(subtree: &Node<T>, kept: Option<Side>) -> usize {
+    let kept = match kept {
+        Some(Side::Left) => subtree.left(),
+        Some(Side::Right) => subtree.right(),
+        None => None,
+    };
+    subtree.len() - kept.map_or(0, |k| k.len())
+}
+
+fn child<T>(node: &Node<T>, side: Side) -> Option<&Node<T>> {
+    match side {
+        Side::Left => node.left(),
+        Side::Right => node.right(),
+    }
+}
+
+impl<T: PartialEq> Node<T> {
+    // selfを古い木、otherを新しい木として、同じ位置のノード同士を比べる　結果は行きがけ順
+    // 片方にしかない部分木はまとめて1つのInserted/Deletedにする
+    // 位置がずれただけの部分木は、次の2つの場合に限って対応付ける
+    //   ノードを1つ挟んで1段下がった(上がった)　→ keptを付けたInserted(Deleted)を1つ
+    //   親の左右の反対側に移った　→ Movedを1つ
+    pub fn diff<'r>(&'r self, other: &'r Node<T>) -> Vec<TreeChange<'r, T>> {
+        enum Frame<'r, T> {
+            Compare(usize, Option<Side>, &'r Node<T>, &'r Node<T>),
+            Emit(TreeChange<'r, T>),
+        }
+
+        // 経路は1本のVecを深さで切り詰めながら使い回し、変更を記録するときだけNodePathにする
+        let mut path: Vec<Side> = Vec::new();
+        let mut changes = Vec::new();
+        let mut stack = vec![Frame::Compare(0, None, self, other)];
+
+        while let Some(frame) = stack.pop() {
+            let (old, new) = match frame {
+                Frame::Emit(change) => { changes.push(change); continue; },
+                Frame::Compare(depth, side, old, new) => {
+                    path.truncate(depth);
+                    path.extend(side);
+                    (old, new)
+                },
+            };
+            let here = || NodePath::from(path.clone());
+
+            if old.data != new.data {
+                let wrapped = [Side::Left, Side::Right].iter().copied()
+                    .find(|s| child(new, *s) == Some(old));
+                if let Some(side) = wrapped {
+                    changes.push(TreeChange::Inserted { path: here(), subtree: new, kept: Some(side) });
+                    continue;
+                }
+                let unwrapped = [Side::Left, Side::Right].iter().copied()
+                    .find(|s| child(old, *s) == Some(new));
+                if let Some(side) = unwrapped {
+                    changes.push(TreeChange::Deleted { path: here(), subtree: old, kept: Some(side) });
+                    continue;
+                }
+                changes.push(TreeChange::Changed { path: here(), old: &old.data, new: &new.data });
+            }
+
+            // 片側の子だけが反対側に移っている
+            if let (Some(a), None, None, Some(b)) = (old.left(), old.right(), new.left(), new.right()) {
+                if a == b {
+                    let (from, to) = (here().join(Side::Left), here().join(Side::Right));
+                    changes.push(TreeChange::Moved { from, path: to, subtree: b });
+                    continue;
+                }
+            }
+            if let (None, Some(a), Some(b), None) = (old.left(), old.right(), new.left(), new.right()) {
+                if a == b {
+                    let (from, to) = (here().join(Side::Right), here().join(Side::Left));
+                    changes.push(TreeChange::Moved { from, path: to, subtree: b });
+                    continue;
+                }
+            }
+
+            // 左を先に処理するので、右から積む
+            let depth = path.len();
+            for side in [Side::Right, Side::Left].iter().copied() {
+                match (child(old, side), child(new, side)) {
+                    (Some(a), Some(b)) => stack.push(Frame::Compare(depth, Some(side), a, b)),
+                    (Some(a), None) => stack.push(Frame::Emit(TreeChange::Deleted { path: here().join(side), subtree: a, kept: None })),
+                    (None, Some(b)) => stack.push(Frame::Emit(TreeChange::Inserted { path: here().join(side), subtree: b, kept: None })),
+                    (None, None) => (),
+                }
+            }
+        }
+
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tree(s: &str) -> Node<i32> {
+        Node::from_sexpr(s).unwrap()
+    }
+
+    #[test]
+    fn no_change() {
+        let a = tree("(1 (2 4 5) (3 6 7))");
+        assert!(a.diff(&a.clone()).is_empty());
+    }
+
+    #[test]
+    fn changes() {
+        let old = tree("(1 (2 4 5) (3 6 7))");
+        let new = tree("(1 (20 4 _) (3 (6 8 9) 7))");
+
+        let changes = old.diff(&new);
+        let paths: Vec<String> = changes.iter().map(|c| c.path().to_string()).collect();
+        assert_eq!(paths, vec!["/L", "/L/R", "/R/L/L", "/R/L/R"]);
+
+        assert_eq!(changes[0], TreeChange::Changed { path: NodePath::from(vec![Side::Left]), old: &2, new: &20 });
+        match &changes[1] {
+            TreeChange::Deleted { subtree, kept: None, .. } => assert_eq!(subtree.as_ref(), &5),
+            c => panic!("unexpected change: {}", c),
+        }
+
+        let report: Vec<String> = changes.iter().map(|c| c.to_string()).collect();
+        assert_eq!(report, vec![
+            "changed  /L: 2 -> 20",
+            "deleted  /L/R: 5 (1 nodes)",
+            "inserted /R/L/L: 8 (1 nodes)",
+            "inserted /R/L/R: 9 (1 nodes)",
+        ]);
+    }
+
+    #[test]
+    fn whole_subtree() {
+        let old = tree("(1 2 (3 6 7))");
+        let new = tree("(0 2 _)");
+
+        let report: Vec<String> = old.diff(&new).iter().map(|c| c.to_string()).collect();
+        assert_eq!(report, vec!["changed  /: 1 -> 0", "deleted  /R: 3 (3 nodes)"]);
+
+        let report: Vec<String> = new.diff(&old).iter().map(|c| c.to_string()).collect();
+        assert_eq!(report, vec!["changed  /: 0 -> 1", "inserted /R: 3 (3 nodes)"]);
+    }
+
+    #[test]
+    fn wrap_and_unwrap() {
+        // 根の上にノードを1つ足す　元の木は丸ごと左に下がる
+        let old = tree("(1 (2 4 5) (3 6 7))");
+        let new = tree("(0 (1 (2 4 5) (3 6 7)) 9)");
+
+        let changes = old.diff(&new);
+        assert_eq!(changes, vec![TreeChange::Inserted { path: NodePath::root(), subtree: &new, kept: Some(Side::Left) }]);
+        assert_eq!(changes[0].to_string(), "inserted /: 0 (2 nodes, old / moved to /L)");
+
+        let report: Vec<String> = new.diff(&old).iter().map(|c| c.to_string()).collect();
+        assert_eq!(report, vec!["deleted  /: 0 (2 nodes, old /L moved to /)"]);
+
+        // 途中の部分木を包む
+        let new = tree("(1 (2 4 5) (8 _ (3 6 7)))");
+        let report: Vec<String> = old.diff(&new).iter().map(|c| c.to_string()).collect();
+        assert_eq!(report, vec!["inserted /R: 8 (1 nodes, old /R moved to /R/R)"]);
+    }
+
+    #[test]
+    fn moved_to_other_side() {
+        let old = tree("(1 (2 (4 8 9) _) 3)");
+        let new = tree("(1 (2 _ (4 8 9)) 3)");
+
+        let report: Vec<String> = old.diff(&new).iter().map(|c| c.to_string()).collect();
+        assert_eq!(report, vec!["moved    /L/L -> /L/R: 4 (3 nodes)"]);
+
+        // 中身も変わっていれば位置ごとの差分になる
+        let new = tree("(1 (2 _ (4 8 10)) 3)");
+        let report: Vec<String> = old.diff(&new).iter().map(|c| c.to_string()).collect();
+        assert_eq!(report, vec!["deleted  /L/L: 4 (3 nodes)", "inserted /L/R: 4 (3 nodes)"]);
+    }
+}
@@ -0,0 +1,146 @@
+use std::fmt::{Display, Write};
+
+use super::{Node, Side};
+
+// to_dot_with()のコールバックが返す、ノード1つ分の表示設定
+#[derive(Debug, Clone, PartialEq)]
+pub struct DotStyle {
+    label: String,
+    attrs: Vec<(String, String)>,
+}
+
+impl DotStyle {
+    pub fn new(label: &str) -> Self {
+        DotStyle {
+            label: label.to_string(),
+            attrs: Vec::new(),
+        }
+    }
+
+    // color, shape, style などGraphvizのノード属性を追加する
+    pub fn attr(mut self, key: &str, value: &str) -> Self {
+        self.attrs.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    fn write(&self, out: &mut String) {
+        write!(out, "label=\"{}\"", escape(&self.label)).unwrap();
+        for (k, v) in self.attrs.iter() {
+            write!(out, ", {}=\"{}\"", k, escape(v)).unwrap();
+        }
+    }
+}
+
+fn escape(s: &str) -> String {
+    let mut ret = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => ret.push_str("\\\""),
+            '\\' => ret.push_str("\\\\"),
+            '\n' => ret.push_str("\\n"),
+            _ => ret.push(c),
+        }
+    }
+    ret
+}
+
+impl<T> Node<T> {
+    // Graphvizのdigraphを出力する　ラベルはDisplayで作る
+    pub fn to_dot(&self) -> String
+    where T: Display {
+        self.to_dot_with(|x| DotStyle::new(&x.to_string()))
+    }
+
+    // ノードごとのラベルや属性をコールバックで決める
+    // 子が片方しかないノードには、もう片方に空のノードを置いて左右がわかるようにする
+    pub fn to_dot_with<F>(&self, mut style: F) -> String
+    where F: FnMut(&T) -> DotStyle {
+        let mut out = String::from("digraph tree {\n");
+        let mut next_id = 1;
+        let mut stack = vec![(self, 0)];
+
+        while let Some((node, id)) = stack.pop() {
+            write!(out, "    n{} [", id).unwrap();
+            style(&node.data).write(&mut out);
+            out.push_str("];\n");
+
+            let is_leaf = node.left.is_none() && node.right.is_none();
+            let mut children = Vec::new();
+
+            for side in &[Side::Left, Side::Right] {
+                let (child, edge) = match side {
+                    Side::Left => (node.left(), "L"),
+                    Side::Right => (node.right(), "R"),
+                };
+
+                match child {
+                    Some(c) => {
+                        writeln!(out, "    n{} -> n{} [label=\"{}\"];", id, next_id, edge).unwrap();
+                        children.push((c, next_id));
+                    },
+                    None if !is_leaf => {
+                        writeln!(out, "    nil{} [label=\"\", shape=point];", next_id).unwrap();
+                        writeln!(out, "    n{} -> nil{} [label=\"{}\", style=dashed];", id, next_id, edge).unwrap();
+                    },
+                    None => continue,
+                }
+                next_id += 1;
+            }
+
+            // 左の子から先に出力されるように、逆順に積む
+            while let Some(c) = children.pop() {
+                stack.push(c);
+            }
+        }
+
+        out.push_str("}\n");
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_dot() {
+        let mut root = Node::new(1);
+        root.create_left_node(2).unwrap().create_right_node(4).unwrap();
+        root.create_right_node(3).unwrap();
+
+        let expected = "\
+digraph tree {
+    n0 [label=\"1\"];
+    n0 -> n1 [label=\"L\"];
+    n0 -> n2 [label=\"R\"];
+    n1 [label=\"2\"];
+    nil3 [label=\"\", shape=point];
+    n1 -> nil3 [label=\"L\", style=dashed];
+    n1 -> n4 [label=\"R\"];
+    n4 [label=\"4\"];
+    n2 [label=\"3\"];
+}
+";
+        assert_eq!(root.to_dot(), expected);
+    }
+
+    #[test]
+    fn to_dot_with() {
+        let mut root = Node::new("a \"quoted\" label");
+        root.create_left_node("x").unwrap();
+        root.create_right_node("y").unwrap();
+
+        let dot = root.to_dot_with(|x| {
+            if x.len() == 1 {
+                DotStyle::new(&x.to_uppercase()).attr("color", "red")
+            } else {
+                DotStyle::new(x).attr("shape", "box")
+            }
+        });
+
+        assert!(dot.contains("n0 [label=\"a \\\"quoted\\\" label\", shape=\"box\"];"));
+        assert!(dot.contains("n1 [label=\"X\", color=\"red\"];"));
+        assert!(dot.contains("n2 [label=\"Y\", color=\"red\"];"));
+        assert!(!dot.contains("nil"));
+    }
+}
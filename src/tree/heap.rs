@@ -0,0 +1,299 @@
+use std::mem;
+
+use super::{Node, NodePath, Side};
+
+// TreeHeapの内部表現
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum HeapRepr {
+    Array,  // Vecに配列レイアウトで詰める
+    Boxed,  // Nodeの完全2分木
+}
+
+#[derive(Debug)]
+enum Repr<T> {
+    Array(Vec<T>),
+    Boxed(Option<Node<T>>, usize),  // 根, 要素数
+}
+
+// 最大値を取り出す優先度付きキュー(2分ヒープ)
+// 内部表現はset_reprで配列とNodeの木の間で切り替えられる　どちらも計算量は同じ
+#[derive(Debug)]
+pub struct TreeHeap<T> {
+    repr: Repr<T>,
+}
+
+impl<T: Ord> TreeHeap<T> {
+    pub fn new() -> Self {
+        Self::with_repr(HeapRepr::Array)
+    }
+
+    pub fn with_repr(repr: HeapRepr) -> Self {
+        TreeHeap {
+            repr: match repr {
+                HeapRepr::Array => Repr::Array(Vec::new()),
+                HeapRepr::Boxed => Repr::Boxed(None, 0),
+            },
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match &self.repr {
+            Repr::Array(v) => v.len(),
+            Repr::Boxed(_, len) => *len,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn repr(&self) -> HeapRepr {
+        match self.repr {
+            Repr::Array(_) => HeapRepr::Array,
+            Repr::Boxed(..) => HeapRepr::Boxed,
+        }
+    }
+
+    // 内部表現を切り替える　要素の並びはそのまま引き継ぐ
+    pub fn set_repr(&mut self, repr: HeapRepr) {
+        if self.repr() == repr {
+            return;
+        }
+
+        let old = mem::replace(&mut self.repr, Repr::Array(Vec::new()));
+        self.repr = match old {
+            Repr::Array(v) => {
+                let len = v.len();
+                let root = Node::from_array_layout(v.into_iter().map(Some).collect()).ok();
+                Repr::Boxed(root, len)
+            },
+            Repr::Boxed(root, _) => {
+                // ヒープの木は完全2分木なので、配列の長さは要素数と同じになり失敗しない
+                let v = root.map(|r| r.into_array_layout().unwrap()).unwrap_or_default();
+                Repr::Array(v.into_iter().flatten().collect())
+            },
+        };
+    }
+
+    pub fn peek(&self) -> Option<&T> {
+        match &self.repr {
+            Repr::Array(v) => v.first(),
+            Repr::Boxed(root, _) => root.as_ref().map(|r| &r.data),
+        }
+    }
+
+    pub fn push(&mut self, x: T) {
+        match &mut self.repr {
+            Repr::Array(v) => {
+                v.push(x);
+                let mut i = v.len() - 1;
+                while i > 0 && v[(i - 1) / 2] < v[i] {
+                    v.swap(i, (i - 1) / 2);
+                    i = (i - 1) / 2;
+                }
+            },
+            Repr::Boxed(root, len) => {
+                match root {
+                    None => *root = Some(Node::new(x)),
+                    Some(r) => boxed_push(r, *len, x),
+                }
+                *len += 1;
+            },
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        match &mut self.repr {
+            Repr::Array(v) => {
+                if v.is_empty() {
+                    return None;
+                }
+                let last = v.len() - 1;
+                v.swap(0, last);
+                let top = v.pop();
+
+                let mut i = 0;
+                loop {
+                    let (l, r) = (2 * i + 1, 2 * i + 2);
+                    let mut largest = i;
+                    if l < v.len() && v[l] > v[largest] {
+                        largest = l;
+                    }
+                    if r < v.len() && v[r] > v[largest] {
+                        largest = r;
+                    }
+                    if largest == i {
+                        break;
+                    }
+                    v.swap(i, largest);
+                    i = largest;
+                }
+                top
+            },
+            Repr::Boxed(root, len) => {
+                if *len <= 1 {
+                    *len = 0;
+                    return root.take().map(|r| r.into_data());
+                }
+                let r = root.as_mut().unwrap();
+                *len -= 1;
+                Some(boxed_pop(r, *len))
+            },
+        }
+    }
+
+    // 大きい順に並べたVecにする
+    pub fn into_sorted_vec(mut self) -> Vec<T> {
+        let mut v = Vec::with_capacity(self.len());
+        while let Some(x) = self.pop() {
+            v.push(x);
+        }
+        v
+    }
+}
+
+impl<T: Ord> Default for TreeHeap<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// 配列レイアウトでの位置idxのノードへの経路
+// idx + 1を2進数で書いたとき、先頭の1を除いた各ビットが0なら左、1なら右
+fn heap_path(idx: usize) -> NodePath {
+    let n = idx + 1;
+    let bits = usize::BITS - n.leading_zeros() - 1;
+    (0..bits).rev().map(|b| if (n >> b) & 1 == 0 { Side::Left } else { Side::Right }).collect()
+}
+
+// 位置lenに新しいノードを付ける
+// 根から新しい位置まで下りながら、xより小さい要素と入れ替えていく(上への入れ替えと同じ結果になる)
+fn boxed_push<T: Ord>(root: &mut Node<T>, len: usize, mut x: T) {
+    let path = heap_path(len);
+    let (last, rest) = path.sides().split_last().unwrap();
+
+    let mut node = root;
+    for side in rest {
+        if x > node.data {
+            mem::swap(&mut x, &mut node.data);
+        }
+        node = match side {
+            Side::Left => node.left_mut().unwrap(),
+            Side::Right => node.right_mut().unwrap(),
+        };
+    }
+    if x > node.data {
+        mem::swap(&mut x, &mut node.data);
+    }
+
+    match last {
+        Side::Left => node.replace_left(Node::new(x)),
+        Side::Right => node.replace_right(Node::new(x)),
+    }
+}
+
+// 位置lenの(最後の)ノードを外して根に置き、下へ入れ替えていく　元の根の要素を返す
+fn boxed_pop<T: Ord>(root: &mut Node<T>, len: usize) -> T {
+    let path = heap_path(len);
+    let parent = root.get_at_mut(&path.parent().unwrap()).unwrap();
+    let last = match path.sides().last() {
+        Some(Side::Left) => parent.take_left(),
+        _ => parent.take_right(),
+    };
+    let top = mem::replace(&mut root.data, last.unwrap().into_data());
+
+    let mut node = root;
+    loop {
+        let Node { data, left, right } = node;
+        let child = match (&mut **left, &mut **right) {
+            (Some(l), Some(r)) => if r.data > l.data { r } else { l },
+            (Some(l), None) => l,
+            _ => break,
+        };
+        if child.data <= *data {
+            break;
+        }
+        mem::swap(data, &mut child.data);
+        node = child;
+    }
+
+    top
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::test_util::lcg;
+
+    #[test]
+    fn heap_path_test() {
+        assert_eq!(heap_path(0).to_string(), "/");
+        assert_eq!(heap_path(1).to_string(), "/L");
+        assert_eq!(heap_path(2).to_string(), "/R");
+        assert_eq!(heap_path(4).to_string(), "/L/R");
+        assert_eq!(heap_path(5).to_string(), "/R/L");
+    }
+
+    #[test]
+    fn push_pop() {
+        for repr in [HeapRepr::Array, HeapRepr::Boxed].iter() {
+            let mut heap = TreeHeap::with_repr(*repr);
+            for x in [5, 1, 8, 3, 9, 2, 8].iter() {
+                heap.push(*x);
+            }
+            assert_eq!(heap.len(), 7);
+            assert_eq!(heap.peek(), Some(&9));
+            assert_eq!(heap.pop(), Some(9));
+            assert_eq!(heap.into_sorted_vec(), vec![8, 8, 5, 3, 2, 1]);
+        }
+
+        let mut heap: TreeHeap<i32> = TreeHeap::with_repr(HeapRepr::Boxed);
+        assert_eq!(heap.pop(), None);
+        heap.push(1);
+        assert_eq!(heap.pop(), Some(1));
+        assert!(heap.is_empty());
+    }
+
+    #[test]
+    fn switch_repr() {
+        let mut heap = TreeHeap::new();
+        for x in 0..20 {
+            heap.push(x);
+        }
+
+        heap.set_repr(HeapRepr::Boxed);
+        assert_eq!(heap.repr(), HeapRepr::Boxed);
+        assert_eq!(heap.len(), 20);
+        assert_eq!(heap.pop(), Some(19));
+        heap.push(30);
+
+        heap.set_repr(HeapRepr::Array);
+        assert_eq!(heap.repr(), HeapRepr::Array);
+        assert_eq!(heap.pop(), Some(30));
+        assert_eq!(heap.into_sorted_vec(), (0..19).rev().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn compare_with_binary_heap() {
+        let mut seed = 3;
+        let mut expected = std::collections::BinaryHeap::new();
+        let mut heap = TreeHeap::with_repr(HeapRepr::Boxed);
+
+        for i in 0..5000 {
+            if lcg(&mut seed) % 3 == 1 {
+                assert_eq!(heap.pop(), expected.pop());
+            } else {
+                let x = lcg(&mut seed) % 100;
+                heap.push(x);
+                expected.push(x);
+            }
+            assert_eq!(heap.peek(), expected.peek());
+            assert_eq!(heap.len(), expected.len());
+
+            if i % 1000 == 999 {
+                let repr = if heap.repr() == HeapRepr::Array { HeapRepr::Boxed } else { HeapRepr::Array };
+                heap.set_repr(repr);
+            }
+        }
+    }
+}
@@ -0,0 +1,263 @@
+use super::Node;
+use super::avl::{self, AvlMeta};
+use super::ordered::{self, Entry, InvariantError, Link, MapIter, OrderedMap};
+
+// 区間木のノードの付加情報
+#[derive(Debug, Clone, PartialEq)]
+pub struct IntervalMeta<K> {
+    pub height: usize,  // 部分木の高さ(葉は1)
+    pub max: K,         // 部分木に含まれる区間の右端の最大値
+}
+
+type IntervalLink<K, V> = Link<(K, K), V, IntervalMeta<K>>;
+type IntervalNode<K, V> = Node<Entry<(K, K), V, IntervalMeta<K>>>;
+
+// 閉区間[lo, hi]をキーにしたAVL木
+// 各ノードに部分木の右端の最大値を持たせておき、重なる区間を探すときに
+// 最大値が問い合わせの左端より小さい部分木には降りないようにする
+#[derive(Debug)]
+pub struct IntervalTree<K, V> {
+    root: IntervalLink<K, V>,
+    len: usize,
+}
+
+impl<K: Ord + Clone, V> IntervalTree<K, V> {
+    pub fn new() -> Self {
+        IntervalTree {
+            root: None,
+            len: 0,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.root = None;
+        self.len = 0;
+    }
+
+    // 全区間の右端の最大値
+    pub fn max_end(&self) -> Option<&K> {
+        self.root().map(|n| &n.data.meta.max)
+    }
+
+    // 端点は小さい方を左端として扱う　(5, 1)と(1, 5)は同じ区間になる
+    fn interval(a: K, b: K) -> (K, K) {
+        if a <= b { (a, b) } else { (b, a) }
+    }
+
+    // 同じ区間が既にある場合は値を置き換え、古い値を返す
+    pub fn insert(&mut self, lo: K, hi: K, value: V) -> Option<V> {
+        let ret = avl::insert_at(&mut self.root, Self::interval(lo, hi), value);
+        if ret.is_none() {
+            self.len += 1;
+        }
+        ret
+    }
+
+    pub fn get(&self, lo: &K, hi: &K) -> Option<&V> {
+        ordered::find(self.root(), &Self::interval(lo.clone(), hi.clone())).map(|n| &n.data.value)
+    }
+
+    pub fn remove(&mut self, lo: &K, hi: &K) -> Option<V> {
+        let ret = avl::remove_at(&mut self.root, &Self::interval(lo.clone(), hi.clone()));
+        if ret.is_some() {
+            self.len -= 1;
+        }
+        ret
+    }
+
+    // [lo, hi]と重なる(端点が接するものも含む)区間を、区間の昇順に返す
+    pub fn overlapping(&self, lo: &K, hi: &K) -> Vec<(&(K, K), &V)> {
+        let (lo, hi) = if lo <= hi { (lo, hi) } else { (hi, lo) };
+        let mut found = Vec::new();
+        let mut stack = Vec::new();
+        let mut node = self.root();
+
+        // 通りがけ順に辿り、重なりえない部分木は飛ばす
+        loop {
+            while let Some(n) = node {
+                if n.data.meta.max < *lo {
+                    break;
+                }
+                stack.push(n);
+                node = n.left();
+            }
+
+            let n = match stack.pop() {
+                Some(n) => n,
+                None => break,
+            };
+
+            let (start, end) = &n.data.key;
+            if start > hi {
+                // これより右のノードは左端がさらに大きい
+                break;
+            }
+            if end >= lo {
+                found.push((&n.data.key, &n.data.value));
+            }
+            node = n.right();
+        }
+
+        found
+    }
+
+    // キーの順序、要素数、部分木の要素数、高さ、左右の高さの差、右端の最大値を確かめる
+    pub fn check_invariants(&self) -> Result<(), InvariantError> {
+        ordered::check_order(self.root(), self.len)?;
+        avl::check_node(self.root())?;
+        ordered::check_size(self.root())
+    }
+}
+
+// 高さに加えて、部分木の右端の最大値を子から計算し直す
+impl<K: Ord + Clone, V> AvlMeta<(K, K), V> for IntervalMeta<K> {
+    fn leaf(key: &(K, K)) -> Self {
+        IntervalMeta { height: 1, max: key.1.clone() }
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn set_height(&mut self, height: usize) {
+        self.height = height;
+    }
+
+    fn recalc(node: &mut IntervalNode<K, V>) {
+        node.data.meta.max = max_of(node);
+    }
+
+    fn check(node: &IntervalNode<K, V>) -> Result<(), InvariantError> {
+        if node.data.meta.max != max_of(node) {
+            return Err( InvariantError::Augment );
+        }
+        Ok(())
+    }
+}
+
+// 自分の右端と、左右の子が持っている最大値のうち最大のもの
+fn max_of<K: Ord + Clone, V>(node: &IntervalNode<K, V>) -> K {
+    let mut max = &node.data.key.1;
+    for child in [node.left(), node.right()].iter().flatten() {
+        if child.data.meta.max > *max {
+            max = &child.data.meta.max;
+        }
+    }
+    max.clone()
+}
+
+impl<K: Ord + Clone, V> Default for IntervalTree<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// キーは区間(lo, hi)を辞書式の順序で並べたもの
+impl<K: Ord, V> OrderedMap<(K, K), V> for IntervalTree<K, V> {
+    type Meta = IntervalMeta<K>;
+
+    fn root(&self) -> Option<&IntervalNode<K, V>> {
+        self.root.as_deref()
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+ordered::impl_map_into_iter!(IntervalTree, (K, K), IntervalMeta<K>);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::test_util::lcg;
+
+    fn keys(found: Vec<(&(i32, i32), &&str)>) -> Vec<(i32, i32)> {
+        found.into_iter().map(|(k, _)| *k).collect()
+    }
+
+    #[test]
+    fn overlap_query() {
+        let mut tree = IntervalTree::new();
+        tree.insert(15, 20, "a");
+        tree.insert(10, 30, "b");
+        tree.insert(17, 19, "c");
+        tree.insert(5, 20, "d");
+        tree.insert(12, 15, "e");
+        tree.insert(30, 40, "f");
+        tree.check_invariants().unwrap();
+
+        assert_eq!(tree.max_end(), Some(&40));
+        assert_eq!(keys(tree.overlapping(&6, &7)), vec![(5, 20)]);
+        assert_eq!(keys(tree.overlapping(&21, &29)), vec![(10, 30)]);
+        // 端点が接するものも重なりとみなす
+        assert_eq!(keys(tree.overlapping(&30, &30)), vec![(10, 30), (30, 40)]);
+        assert_eq!(keys(tree.overlapping(&16, &18)), vec![(5, 20), (10, 30), (15, 20), (17, 19)]);
+        assert!(tree.overlapping(&41, &50).is_empty());
+        assert!(tree.overlapping(&0, &4).is_empty());
+
+        assert_eq!(tree.insert(17, 19, "g"), Some("c"));
+        assert_eq!(tree.get(&17, &19), Some(&"g"));
+        assert_eq!(tree.len(), 6);
+
+        let keys: Vec<(i32, i32)> = (&tree).into_iter().map(|(k, _)| *k).collect();
+        assert_eq!(keys, vec![(5, 20), (10, 30), (12, 15), (15, 20), (17, 19), (30, 40)]);
+    }
+
+    #[test]
+    fn reversed_endpoints() {
+        let mut tree = IntervalTree::new();
+
+        assert_eq!(tree.insert(20, 10, "a"), None);
+        assert_eq!(tree.insert(10, 20, "b"), Some("a"));
+        assert_eq!(tree.get(&20, &10), Some(&"b"));
+        assert_eq!(tree.max_end(), Some(&20));
+        assert_eq!(keys(tree.overlapping(&25, &15)), vec![(10, 20)]);
+        assert_eq!(tree.remove(&20, &10), Some("b"));
+        assert!(tree.is_empty());
+    }
+
+    #[test]
+    fn remove() {
+        let mut tree = IntervalTree::new();
+        for i in 0..100 {
+            tree.insert(i, i + 10 + (i % 7) * 5, i);
+        }
+        tree.check_invariants().unwrap();
+
+        for i in (0..100).filter(|i| i % 3 != 1) {
+            assert_eq!(tree.remove(&i, &(i + 10 + (i % 7) * 5)), Some(i));
+            tree.check_invariants().unwrap();
+        }
+        assert_eq!(tree.remove(&0, &10), None);
+        assert_eq!(tree.len(), 33);
+        assert_eq!(tree.max_end(), Some(&(97 + 10 + 5 * 6)));
+    }
+
+    #[test]
+    fn compare_with_linear_scan() {
+        let mut seed = 5;
+        let mut tree = IntervalTree::new();
+        let mut expected: Vec<(i32, i32)> = Vec::new();
+
+        for _ in 0..2000 {
+            let lo = (lcg(&mut seed) % 1000) as i32;
+            let hi = lo + (lcg(&mut seed) % 50) as i32;
+
+            if lcg(&mut seed) % 3 == 1 && !expected.is_empty() {
+                let (lo, hi) = expected.remove((lcg(&mut seed) as usize) % expected.len());
+                assert!(tree.remove(&lo, &hi).is_some());
+            } else if tree.insert(lo, hi, ()).is_none() {
+                expected.push((lo, hi));
+            }
+
+            let a = (lcg(&mut seed) % 1000) as i32;
+            let b = a + (lcg(&mut seed) % 30) as i32;
+            let mut want: Vec<(i32, i32)> = expected.iter().copied().filter(|&(lo, hi)| lo <= b && a <= hi).collect();
+            want.sort();
+            let got: Vec<(i32, i32)> = tree.overlapping(&a, &b).into_iter().map(|(k, _)| *k).collect();
+            assert_eq!(got, want);
+        }
+        tree.check_invariants().unwrap();
+    }
+}
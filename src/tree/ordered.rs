@@ -0,0 +1,385 @@
+use std::cmp::Ordering;
+use std::iter::FusedIterator;
+use std::ops::{Bound, RangeBounds};
+
+use super::{Node, NodeIter, SearchOrder};
+
+// 順序付きの木(BstMap, AvlTree, ...)のノードに入れるデータ
+// metaには木の種類ごとの付加情報(AVL木なら高さなど)を入れる
+// sizeはそのノードを根とする部分木の要素数　どの木でも保守し、順位の計算に使う
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry<K, V, M = ()> {
+    pub key: K,
+    pub value: V,
+    pub(super) meta: M,
+    pub(super) size: usize,
+}
+
+impl<K, V, M> Entry<K, V, M> {
+    pub(super) fn new(key: K, value: V, meta: M) -> Self {
+        Entry {
+            key,
+            value,
+            meta,
+            size: 1,
+        }
+    }
+
+    pub fn meta(&self) -> &M {
+        &self.meta
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+}
+
+// 木の不変条件が崩れていたときに返すエラー
+#[derive(Debug, Eq, PartialEq)]
+pub enum InvariantError {
+    KeyOrder,       // 通りがけ順でキーが昇順に並んでいない
+    Length,         // 保持している要素数と実際のノード数が違う
+    Height,         // 保持している高さと実際の高さが違う
+    Balance,        // 左右の高さの差が1より大きい
+    RootColor,      // 根が黒ではない
+    RedRed,         // 赤のノードの子が赤
+    BlackHeight,    // 根から葉までの経路によって黒のノードの数が違う
+    Priority,       // 親の優先度が子の優先度より低い
+    Augment,        // 保持している部分木の集約値(区間の最大端点や要素数)が、子から計算し直した値と違う
+}
+
+pub(super) type Link<K, V, M> = Option<Box<Node<Entry<K, V, M>>>>;
+
+// 順序付きの木(BstMap, AvlTree, RbTree, SplayTree, Treap)に共通の読み取り操作
+// 根と要素数さえ返せば、残りはノードのEntryに保守している要素数を使って既定の実装で求まる
+pub trait OrderedMap<K: Ord, V> {
+    type Meta;
+
+    fn root(&self) -> Option<&Node<Entry<K, V, Self::Meta>>>;
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // 木の形を変えずに探す　戻り値にKが現れないので、根を借りられるようにKとMetaの寿命を明示する
+    fn get<'r>(&'r self, key: &K) -> Option<&'r V>
+    where K: 'r, Self::Meta: 'r {
+        find(self.root(), key).map(|n| &n.data.value)
+    }
+
+    fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    fn min(&self) -> Option<(&K, &V)> {
+        first(self.root()).map(key_value)
+    }
+
+    fn max(&self) -> Option<(&K, &V)> {
+        last(self.root()).map(key_value)
+    }
+
+    // k番目(0始まり)に小さいキー
+    fn select(&self, k: usize) -> Option<(&K, &V)> {
+        select(self.root(), k).map(key_value)
+    }
+
+    // keyより小さいキーの数
+    fn rank(&self, key: &K) -> usize {
+        rank(self.root(), key)
+    }
+
+    // 要素数が偶数のときは小さい方
+    fn median(&self) -> Option<(&K, &V)> {
+        self.percentile(50.0)
+    }
+
+    fn percentile(&self, p: f64) -> Option<(&K, &V)> {
+        percentile_rank(self.len(), p).and_then(|k| self.select(k))
+    }
+
+    fn iter(&self) -> MapIter<'_, K, V, Self::Meta> {
+        MapIter::new(self.root())
+    }
+
+    fn range<R: RangeBounds<K>>(&self, range: R) -> MapRange<'_, K, V, R, Self::Meta> {
+        MapRange::new(self.root(), range)
+    }
+}
+
+// &木 でfor文を回せるようにする　外部のトレイトなのでOrderedMapの既定の実装にはできない
+// キーがKそのものでない木(区間木など)はキーの型も渡す
+macro_rules! impl_map_into_iter {
+    ($map:ident, $meta:ty) => {
+        ordered::impl_map_into_iter!($map, K, $meta);
+    };
+    ($map:ident, $key:ty, $meta:ty) => {
+        impl<'r, K: Ord, V> IntoIterator for &'r $map<K, V> {
+            type Item = (&'r $key, &'r V);
+            type IntoIter = MapIter<'r, $key, V, $meta>;
+
+            fn into_iter(self) -> MapIter<'r, $key, V, $meta> {
+                self.iter()
+            }
+        }
+    };
+}
+
+pub(super) use impl_map_into_iter;
+
+pub(super) fn find<'r, K: Ord, V, M>(root: Option<&'r Node<Entry<K, V, M>>>, key: &K) -> Option<&'r Node<Entry<K, V, M>>> {
+    let mut cur = root;
+
+    while let Some(node) = cur {
+        match key.cmp(&node.data.key) {
+            Ordering::Less => cur = node.left(),
+            Ordering::Greater => cur = node.right(),
+            Ordering::Equal => return Some(node),
+        }
+    }
+    None
+}
+
+// keyを持つノードへのリンク(なければ挿入すべき位置の空のリンク)を返す
+pub(super) fn find_link<'a, K: Ord, V, M>(mut link: &'a mut Link<K, V, M>, key: &K) -> &'a mut Link<K, V, M> {
+    loop {
+        match link.as_ref().map(|n| key.cmp(&n.data.key)) {
+            Some(Ordering::Less) => link = &mut link.as_mut().unwrap().left,
+            Some(Ordering::Greater) => link = &mut link.as_mut().unwrap().right,
+            _ => return link,
+        }
+    }
+}
+
+// 値を書き換えるには根へのリンクが要るので、トレイトではなく各木から呼ぶ
+pub(super) fn get_mut<'a, K: Ord, V, M>(link: &'a mut Link<K, V, M>, key: &K) -> Option<&'a mut V> {
+    find_link(link, key).as_mut().map(|n| &mut n.data.value)
+}
+
+pub(super) fn first<T>(root: Option<&Node<T>>) -> Option<&Node<T>> {
+    let mut node = root?;
+    while let Some(l) = node.left() {
+        node = l;
+    }
+    Some(node)
+}
+
+pub(super) fn last<T>(root: Option<&Node<T>>) -> Option<&Node<T>> {
+    let mut node = root?;
+    while let Some(r) = node.right() {
+        node = r;
+    }
+    Some(node)
+}
+
+pub(super) fn key_value<K, V, M>(node: &Node<Entry<K, V, M>>) -> (&K, &V) {
+    (&node.data.key, &node.data.value)
+}
+
+pub(super) fn size_of<K, V, M>(node: Option<&Node<Entry<K, V, M>>>) -> usize {
+    node.map_or(0, |n| n.data.size)
+}
+
+// 子の要素数が正しいことを前提に、nodeの要素数を計算し直す
+pub(super) fn update_size<K, V, M>(node: &mut Node<Entry<K, V, M>>) {
+    node.data.size = 1 + size_of(node.left()) + size_of(node.right());
+}
+
+// 小さい方からk番目(0始まり)のノード　木の高さに比例する時間で求まる
+pub(super) fn select<K, V, M>(root: Option<&Node<Entry<K, V, M>>>, mut k: usize) -> Option<&Node<Entry<K, V, M>>> {
+    let mut cur = root;
+
+    while let Some(node) = cur {
+        let left = size_of(node.left());
+        match k.cmp(&left) {
+            Ordering::Less => cur = node.left(),
+            Ordering::Equal => return Some(node),
+            Ordering::Greater => {
+                k -= left + 1;
+                cur = node.right();
+            }
+        }
+    }
+    None
+}
+
+// keyより小さいキーの数
+pub(super) fn rank<K: Ord, V, M>(root: Option<&Node<Entry<K, V, M>>>, key: &K) -> usize {
+    let mut cur = root;
+    let mut cnt = 0;
+
+    while let Some(node) = cur {
+        match key.cmp(&node.data.key) {
+            Ordering::Less => cur = node.left(),
+            Ordering::Equal => return cnt + size_of(node.left()),
+            Ordering::Greater => {
+                cnt += size_of(node.left()) + 1;
+                cur = node.right();
+            }
+        }
+    }
+    cnt
+}
+
+// p(0〜100)パーセンタイルに当たる順位(0始まり)　最近接順位法で、pの割合以上を覆う最小の順位を選ぶ
+pub(super) fn percentile_rank(len: usize, p: f64) -> Option<usize> {
+    if len == 0 || !(0.0..=100.0).contains(&p) {
+        return None;
+    }
+    let k = (p / 100.0 * len as f64).ceil() as usize;
+    Some(k.saturating_sub(1).min(len - 1))
+}
+
+// 木を壊してキーと値を取り出す　順序は問わない
+pub(super) fn into_entries<K, V, M>(root: Link<K, V, M>) -> Vec<(K, V)> {
+    let mut stack: Vec<Box<Node<Entry<K, V, M>>>> = root.into_iter().collect();
+    let mut entries = Vec::new();
+
+    while let Some(mut node) = stack.pop() {
+        stack.extend(node.left.take());
+        stack.extend(node.right.take());
+        let e = node.into_data();
+        entries.push((e.key, e.value));
+    }
+    entries
+}
+
+// 各ノードの要素数が、子から計算し直した値と一致しているか
+pub(super) fn check_size<K, V, M>(root: Option<&Node<Entry<K, V, M>>>) -> Result<(), InvariantError> {
+    let root = match root {
+        Some(r) => r,
+        None => return Ok(()),
+    };
+    root.try_fold(|e, left, right| {
+        let size = 1 + left.unwrap_or(0) + right.unwrap_or(0);
+        if e.size == size { Ok(size) } else { Err( InvariantError::Augment ) }
+    }).map(|_| ())
+}
+
+// 通りがけ順でキーが昇順に並んでいるか
+pub(super) fn check_order<K: Ord, V, M>(root: Option<&Node<Entry<K, V, M>>>, len: usize) -> Result<(), InvariantError> {
+    let mut cnt = 0;
+    let mut prev: Option<&K> = None;
+
+    // 保持している要素数は壊れているかもしれないので、MapIterは使わずにノードを直接たどる
+    for k in root.into_iter().flat_map(|n| n.iter(&SearchOrder::InOrder)).map(|e| &e.key) {
+        if let Some(p) = prev {
+            if p >= k {
+                return Err( InvariantError::KeyOrder );
+            }
+        }
+        prev = Some(k);
+        cnt += 1;
+    }
+
+    if cnt != len {
+        return Err( InvariantError::Length );
+    }
+    Ok(())
+}
+
+// キーの昇順に返す　残りの要素数は根の部分木の要素数から分かるので、木をたどって数える必要はない
+pub struct MapIter<'r, K, V, M = ()> {
+    inner: Option<NodeIter<'r, Entry<K, V, M>>>,
+    remain: usize,
+}
+
+impl<'r, K, V, M> MapIter<'r, K, V, M> {
+    pub(super) fn new(root: Option<&'r Node<Entry<K, V, M>>>) -> Self {
+        MapIter {
+            inner: root.map(|n| n.iter(&SearchOrder::InOrder)),
+            remain: size_of(root),
+        }
+    }
+}
+
+impl<'r, K, V, M> Iterator for MapIter<'r, K, V, M> {
+    type Item = (&'r K, &'r V);
+
+    fn next(&mut self) -> Option<(&'r K, &'r V)> {
+        let e = self.inner.as_mut()?.next()?;
+        self.remain -= 1;
+        Some((&e.key, &e.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remain, Some(self.remain))
+    }
+}
+
+impl<'r, K, V, M> DoubleEndedIterator for MapIter<'r, K, V, M> {
+    fn next_back(&mut self) -> Option<(&'r K, &'r V)> {
+        let e = self.inner.as_mut()?.next_back()?;
+        self.remain -= 1;
+        Some((&e.key, &e.value))
+    }
+}
+
+impl<'r, K, V, M> ExactSizeIterator for MapIter<'r, K, V, M> {}
+
+impl<'r, K, V, M> FusedIterator for MapIter<'r, K, V, M> {}
+
+// 範囲に入るキーだけを昇順に返す　範囲外の部分木には降りない
+pub struct MapRange<'r, K, V, R, M = ()> {
+    stack: Vec<&'r Node<Entry<K, V, M>>>,
+    range: R,
+}
+
+impl<'r, K: Ord, V, R: RangeBounds<K>, M> MapRange<'r, K, V, R, M> {
+    pub(super) fn new(root: Option<&'r Node<Entry<K, V, M>>>, range: R) -> Self {
+        let mut iter = MapRange {
+            stack: Vec::new(),
+            range,
+        };
+        iter.push_left(root);
+        iter
+    }
+
+    fn after_start(&self, key: &K) -> bool {
+        match self.range.start_bound() {
+            Bound::Included(s) => key >= s,
+            Bound::Excluded(s) => key > s,
+            Bound::Unbounded => true,
+        }
+    }
+
+    fn before_end(&self, key: &K) -> bool {
+        match self.range.end_bound() {
+            Bound::Included(e) => key <= e,
+            Bound::Excluded(e) => key < e,
+            Bound::Unbounded => true,
+        }
+    }
+
+    // 下限より小さいノードは積まずに右へ進む(その左の部分木はすべて範囲外)
+    fn push_left(&mut self, mut node: Option<&'r Node<Entry<K, V, M>>>) {
+        while let Some(n) = node {
+            if self.after_start(&n.data.key) {
+                self.stack.push(n);
+                node = n.left();
+            } else {
+                node = n.right();
+            }
+        }
+    }
+}
+
+impl<'r, K: Ord, V, R: RangeBounds<K>, M> Iterator for MapRange<'r, K, V, R, M> {
+    type Item = (&'r K, &'r V);
+
+    fn next(&mut self) -> Option<(&'r K, &'r V)> {
+        let node = self.stack.pop()?;
+
+        if !self.before_end(&node.data.key) {
+            self.stack.clear();
+            return None;
+        }
+
+        self.push_left(node.right());
+        Some(key_value(node))
+    }
+}
+
+impl<'r, K: Ord, V, R: RangeBounds<K>, M> FusedIterator for MapRange<'r, K, V, R, M> {}
@@ -0,0 +1,272 @@
+use std::fmt;
+use std::iter::FromIterator;
+
+use super::{Node, NodeError, Side};
+
+// 根からノードまでの経路　左右どちらの子へ進むかを根から順に並べたもの
+// 借用を持たないので、木を書き換える前後で位置を覚えておくのに使える
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NodePath(Vec<Side>);
+
+impl NodePath {
+    // 根を指す空の経路
+    pub fn root() -> Self {
+        NodePath(Vec::new())
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    // 経路の長さ = 指しているノードの深さ
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn sides(&self) -> &[Side] {
+        &self.0
+    }
+
+    pub fn push(&mut self, side: Side) {
+        self.0.push(side);
+    }
+
+    pub fn pop(&mut self) -> Option<Side> {
+        self.0.pop()
+    }
+
+    // 子への経路を新しく作る
+    pub fn join(&self, side: Side) -> NodePath {
+        let mut path = self.clone();
+        path.push(side);
+        path
+    }
+
+    // 親への経路　根ならNone
+    pub fn parent(&self) -> Option<NodePath> {
+        if self.is_root() {
+            return None;
+        }
+        Some(NodePath(self.0[..self.0.len() - 1].to_vec()))
+    }
+
+    // selfがotherの子孫(またはother自身)を指しているか
+    pub fn starts_with(&self, other: &NodePath) -> bool {
+        self.0.starts_with(&other.0)
+    }
+
+    // 2つの経路の共通部分　2つのノードの最も近い共通の祖先を指す
+    pub fn common_prefix(&self, other: &NodePath) -> NodePath {
+        self.0.iter().zip(other.0.iter()).take_while(|(a, b)| a == b).map(|(a, _)| *a).collect()
+    }
+}
+
+impl From<Vec<Side>> for NodePath {
+    fn from(sides: Vec<Side>) -> Self {
+        NodePath(sides)
+    }
+}
+
+impl FromIterator<Side> for NodePath {
+    fn from_iter<I: IntoIterator<Item = Side>>(iter: I) -> Self {
+        NodePath(iter.into_iter().collect())
+    }
+}
+
+// 根は "/"、それ以外は "/L/R" のように表示する
+impl fmt::Display for NodePath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_root() {
+            return write!(f, "/");
+        }
+        for side in self.0.iter() {
+            match side {
+                Side::Left => write!(f, "/L")?,
+                Side::Right => write!(f, "/R")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<T> Node<T> {
+    pub fn get_at(&self, path: &NodePath) -> Option<&Node<T>> {
+        let mut node = self;
+        for side in path.sides() {
+            node = match side {
+                Side::Left => node.left()?,
+                Side::Right => node.right()?,
+            };
+        }
+        Some(node)
+    }
+
+    pub fn get_at_mut(&mut self, path: &NodePath) -> Option<&mut Node<T>> {
+        let mut node = self;
+        for side in path.sides() {
+            node = match side {
+                Side::Left => node.left_mut()?,
+                Side::Right => node.right_mut()?,
+            };
+        }
+        Some(node)
+    }
+
+    // pathの部分木を入れ替えて、元の部分木を返す
+    pub fn replace_at(&mut self, path: &NodePath, tree: Node<T>) -> Result<Node<T>, NodeError> {
+        match self.get_at_mut(path) {
+            Some(node) => Ok(std::mem::replace(node, tree)),
+            None => Err( NodeError::NoNode ),
+        }
+    }
+
+    // pathの部分木を切り離して返す　根は切り離せない
+    pub fn take_at(&mut self, path: &NodePath) -> Result<Node<T>, NodeError> {
+        let parent = path.parent().ok_or(NodeError::NoNode)?;
+        let node = self.get_at_mut(&parent).ok_or(NodeError::NoNode)?;
+
+        let child = match path.sides().last() {
+            Some(Side::Left) => node.take_left(),
+            Some(Side::Right) => node.take_right(),
+            None => None,
+        };
+        child.ok_or(NodeError::NoNode)
+    }
+
+    // (根からの経路, 要素) を行きがけ順に返す
+    pub fn iter_with_path(&self) -> PathIter<'_, T> {
+        PathIter {
+            stack: vec![(self, 0, None)],
+            path: Vec::new(),
+        }
+    }
+}
+
+// 経路は1本のVecを深さで切り詰めながら使い回す　返すNodePathだけはその都度複製する
+pub struct PathIter<'r, T> {
+    stack: Vec<(&'r Node<T>, usize, Option<Side>)>,
+    path: Vec<Side>,
+}
+
+impl<'r, T> PathIter<'r, T> {
+    // 次のノードへ進み、経路は複製せずに貸す　findのように一部だけ使う場合はこちらを呼ぶ
+    pub(super) fn advance(&mut self) -> Option<(&[Side], &'r T)> {
+        let (node, depth, side) = self.stack.pop()?;
+
+        self.path.truncate(depth);
+        if let Some(s) = side {
+            self.path.push(s);
+        }
+
+        let depth = self.path.len();
+        self.stack.extend(node.right().map(|r| (r, depth, Some(Side::Right))));
+        self.stack.extend(node.left().map(|l| (l, depth, Some(Side::Left))));
+        Some((&self.path, &node.data))
+    }
+}
+
+impl<'r, T> Iterator for PathIter<'r, T> {
+    type Item = (NodePath, &'r T);
+
+    fn next(&mut self) -> Option<(NodePath, &'r T)> {
+        self.advance().map(|(path, x)| (path.iter().copied().collect(), x))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::test_util::create_test_tree;
+
+    #[test]
+    fn node_path() {
+        let path = NodePath::root().join(Side::Left).join(Side::Right);
+
+        assert_eq!(path.len(), 2);
+        assert_eq!(path.to_string(), "/L/R");
+        assert_eq!(NodePath::root().to_string(), "/");
+        assert_eq!(path.parent(), Some(NodePath::from(vec![Side::Left])));
+        assert_eq!(NodePath::root().parent(), None);
+        assert!(path.starts_with(&NodePath::from(vec![Side::Left])));
+        assert!(!path.starts_with(&NodePath::from(vec![Side::Right])));
+
+        let other: NodePath = vec![Side::Left, Side::Left, Side::Right].into_iter().collect();
+        assert_eq!(path.common_prefix(&other), NodePath::from(vec![Side::Left]));
+    }
+
+    #[test]
+    fn get_at() {
+        let mut root = create_test_tree();
+        let path = NodePath::from(vec![Side::Right, Side::Left]);
+
+        assert_eq!(root.get_at(&path).unwrap().as_ref(), &6);
+        assert_eq!(root.get_at(&NodePath::root()).unwrap().as_ref(), &1);
+        assert!(root.get_at(&path.join(Side::Left)).is_none());
+
+        *root.get_at_mut(&path).unwrap().as_mut() = 60;
+        assert_eq!(root.right().unwrap().left().unwrap().as_ref(), &60);
+    }
+
+    #[test]
+    fn replace_and_take() {
+        let mut root = create_test_tree();
+        let left = NodePath::from(vec![Side::Left]);
+
+        let old = root.replace_at(&left, Node::new(8)).unwrap();
+        assert_eq!(old.len(), 3);
+        assert_eq!(root.get_at(&left).unwrap().as_ref(), &8);
+        assert_eq!(root.replace_at(&left.join(Side::Left), Node::new(9)).unwrap_err(), NodeError::NoNode);
+
+        let right = root.take_at(&NodePath::from(vec![Side::Right])).unwrap();
+        assert_eq!(right.as_ref(), &3);
+        assert!(root.right().is_none());
+        assert_eq!(root.take_at(&NodePath::from(vec![Side::Right])).unwrap_err(), NodeError::NoNode);
+        assert_eq!(root.take_at(&NodePath::root()).unwrap_err(), NodeError::NoNode);
+
+        let old = root.replace_at(&NodePath::root(), right).unwrap();
+        assert_eq!(old.len(), 2);
+        assert_eq!(root.as_ref(), &3);
+    }
+
+    #[test]
+    fn iter_with_path() {
+        let root = create_test_tree();
+
+        let result: Vec<(String, i32)> = root.iter_with_path().map(|(p, x)| (p.to_string(), *x)).collect();
+        assert_eq!(result, vec![
+            ("/".to_string(), 1),
+            ("/L".to_string(), 2),
+            ("/L/L".to_string(), 4),
+            ("/L/R".to_string(), 5),
+            ("/R".to_string(), 3),
+            ("/R/L".to_string(), 6),
+            ("/R/R".to_string(), 7),
+        ]);
+
+        // 経路から同じノードを引ける
+        for (path, x) in root.iter_with_path() {
+            assert_eq!(root.get_at(&path).unwrap().as_ref(), x);
+        }
+    }
+
+    #[test]
+    fn find_deep_chain() {
+        // 経路を毎回複製するとO(n・深さ)になり、この深さでは終わらなくなる
+        let mut root = Node::new(0);
+        let mut cur = &mut root;
+        for i in 1..100000 {
+            cur = cur.create_left_node(i).unwrap();
+        }
+
+        let path = root.find(|x| *x == 99999).unwrap();
+        assert_eq!(path.len(), 99999);
+        assert!(path.sides().iter().all(|s| *s == Side::Left));
+        assert_eq!(root.depth_of(|x| *x == 50000), Some(50000));
+        assert_eq!(root.find(|x| *x < 0), None);
+    }
+}
@@ -0,0 +1,307 @@
+use std::rc::Rc;
+
+use super::{Node, NodeError, NodePath, Side};
+
+type Link<T> = Option<Rc<PNode<T>>>;
+
+#[derive(Debug)]
+struct PNode<T> {
+    data: T,
+    left: Link<T>,
+    right: Link<T>,
+}
+
+// Rcで部分木を共有する、書き換えできない木
+// 変更操作は元の木をそのまま残し、根から変更箇所までの経路だけを作り直した新しい版を返す
+// 経路以外の部分木は古い版と共有するので、1回の変更はO(深さ)で済む
+#[derive(Debug)]
+pub struct PersistentTree<T> {
+    root: Rc<PNode<T>>,
+}
+
+// 版のコピーはRcを増やすだけなので、T: Cloneは要らない
+impl<T> Clone for PersistentTree<T> {
+    fn clone(&self) -> Self {
+        PersistentTree { root: Rc::clone(&self.root) }
+    }
+}
+
+impl<T> PersistentTree<T> {
+    pub fn new(data: T) -> Self {
+        Self::with_children(data, None, None)
+    }
+
+    pub fn with_children(data: T, left: Option<PersistentTree<T>>, right: Option<PersistentTree<T>>) -> Self {
+        PersistentTree {
+            root: Rc::new(PNode {
+                data,
+                left: left.map(|t| t.root),
+                right: right.map(|t| t.root),
+            }),
+        }
+    }
+
+    pub fn data(&self) -> &T {
+        &self.root.data
+    }
+
+    pub fn left(&self) -> Option<PersistentTree<T>> {
+        self.root.left.clone().map(|root| PersistentTree { root })
+    }
+
+    pub fn right(&self) -> Option<PersistentTree<T>> {
+        self.root.right.clone().map(|root| PersistentTree { root })
+    }
+
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    // 2つの版が同じ木(の実体)を指しているか
+    pub fn ptr_eq(&self, other: &PersistentTree<T>) -> bool {
+        Rc::ptr_eq(&self.root, &other.root)
+    }
+
+    pub fn get_at(&self, path: &NodePath) -> Option<PersistentTree<T>> {
+        self.spine(path).map(|s| PersistentTree { root: Rc::clone(s[s.len() - 1]) })
+    }
+
+    // 行きがけ順
+    pub fn iter(&self) -> PersistentIter<'_, T> {
+        PersistentIter { stack: vec![&self.root] }
+    }
+
+    // 根からpathの指すノードまでのノード列
+    fn spine(&self, path: &NodePath) -> Option<Vec<&Rc<PNode<T>>>> {
+        let mut spine = vec![&self.root];
+        for side in path.sides() {
+            let node = spine[spine.len() - 1];
+            let next = match side {
+                Side::Left => node.left.as_ref()?,
+                Side::Right => node.right.as_ref()?,
+            };
+            spine.push(next);
+        }
+        Some(spine)
+    }
+}
+
+impl<T: Clone> PersistentTree<T> {
+    // pathの部分木をtreeに差し替えた版を返す
+    pub fn replace_at(&self, path: &NodePath, tree: PersistentTree<T>) -> Result<PersistentTree<T>, NodeError> {
+        let spine = self.spine(path).ok_or(NodeError::NoNode)?;
+        Ok(Self::rebuild(&spine[..path.len()], path.sides(), Some(tree.root)))
+    }
+
+    // pathの要素だけを差し替えた版を返す　子はそのまま共有する
+    pub fn set_at(&self, path: &NodePath, data: T) -> Result<PersistentTree<T>, NodeError> {
+        let spine = self.spine(path).ok_or(NodeError::NoNode)?;
+        let target = spine[path.len()];
+        let node = Rc::new(PNode {
+            data,
+            left: target.left.clone(),
+            right: target.right.clone(),
+        });
+        Ok(Self::rebuild(&spine[..path.len()], path.sides(), Some(node)))
+    }
+
+    // 空いている位置pathにtreeを付けた版を返す
+    // 親がなければNoNode、既に子があればChildAddError
+    pub fn insert_at(&self, path: &NodePath, tree: PersistentTree<T>) -> Result<PersistentTree<T>, NodeError> {
+        let parent = path.parent().ok_or(NodeError::ChildAddError)?;
+        let spine = self.spine(&parent).ok_or(NodeError::NoNode)?;
+
+        let node = spine[parent.len()];
+        let occupied = match path.sides()[parent.len()] {
+            Side::Left => node.left.is_some(),
+            Side::Right => node.right.is_some(),
+        };
+        if occupied {
+            return Err( NodeError::ChildAddError );
+        }
+
+        Ok(Self::rebuild(&spine, path.sides(), Some(tree.root)))
+    }
+
+    // pathの部分木を取り除いた版を返す　根は取り除けない
+    pub fn remove_at(&self, path: &NodePath) -> Result<PersistentTree<T>, NodeError> {
+        if path.is_root() {
+            return Err( NodeError::NoNode );
+        }
+        let spine = self.spine(path).ok_or(NodeError::NoNode)?;
+        Ok(Self::rebuild(&spine[..path.len()], path.sides(), None))
+    }
+
+    // spine[i]のsides[i]側の子を、下から順にlinkで置き換えながら複製していく
+    // spineが空なら、linkがそのまま新しい根になる
+    fn rebuild(spine: &[&Rc<PNode<T>>], sides: &[Side], mut link: Link<T>) -> PersistentTree<T> {
+        for (node, side) in spine.iter().zip(sides.iter()).rev() {
+            let (left, right) = match side {
+                Side::Left => (link, node.right.clone()),
+                Side::Right => (node.left.clone(), link),
+            };
+            link = Some(Rc::new(PNode {
+                data: node.data.clone(),
+                left,
+                right,
+            }));
+        }
+        PersistentTree { root: link.unwrap() }
+    }
+
+    // 共有を解いた、所有権を持つ木に変換する
+    pub fn to_node(&self) -> Node<T> {
+        let mut stack = vec![(&self.root, false)];
+        let mut done: Vec<Node<T>> = Vec::new();
+
+        while let Some((node, expanded)) = stack.pop() {
+            if !expanded {
+                stack.push((node, true));
+                stack.extend(node.right.as_ref().map(|r| (r, false)));
+                stack.extend(node.left.as_ref().map(|l| (l, false)));
+                continue;
+            }
+
+            let right = if node.right.is_some() { done.pop() } else { None };
+            let left = if node.left.is_some() { done.pop() } else { None };
+
+            done.push(Node {
+                data: node.data.clone(),
+                left: left.map(Box::new).into(),
+                right: right.map(Box::new).into(),
+            });
+        }
+
+        done.pop().unwrap()
+    }
+}
+
+impl<T: Clone> From<&Node<T>> for PersistentTree<T> {
+    fn from(node: &Node<T>) -> Self {
+        let root = node.fold(|x, left, right| Rc::new(PNode {
+            data: x.clone(),
+            left,
+            right,
+        }));
+        PersistentTree { root }
+    }
+}
+
+// 深い木でもスタックが溢れないように、他の版と共有していない部分だけを繰り返しで解放する
+impl<T> Drop for PNode<T> {
+    fn drop(&mut self) {
+        let mut stack: Vec<Rc<PNode<T>>> = Vec::new();
+        stack.extend(self.left.take());
+        stack.extend(self.right.take());
+
+        while let Some(rc) = stack.pop() {
+            if let Ok(mut node) = Rc::try_unwrap(rc) {
+                stack.extend(node.left.take());
+                stack.extend(node.right.take());
+            }
+        }
+    }
+}
+
+pub struct PersistentIter<'r, T> {
+    stack: Vec<&'r Rc<PNode<T>>>,
+}
+
+impl<'r, T> Iterator for PersistentIter<'r, T> {
+    type Item = &'r T;
+
+    fn next(&mut self) -> Option<&'r T> {
+        let node = self.stack.pop()?;
+        self.stack.extend(node.right.as_ref());
+        self.stack.extend(node.left.as_ref());
+        Some(&node.data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::SearchOrder;
+    use crate::tree::test_util;
+
+    fn create_test_tree() -> PersistentTree<i32> {
+        PersistentTree::from(&test_util::create_test_tree())
+    }
+
+    fn path(sides: &[Side]) -> NodePath {
+        NodePath::from(sides.to_vec())
+    }
+
+    #[test]
+    fn replace_shares_subtrees() {
+        let v1 = create_test_tree();
+        let v2 = v1.replace_at(&path(&[Side::Left, Side::Right]), PersistentTree::new(50)).unwrap();
+
+        // 古い版は変わらない
+        assert_eq!(v1.iter().copied().collect::<Vec<_>>(), vec![1, 2, 4, 5, 3, 6, 7]);
+        assert_eq!(v2.iter().copied().collect::<Vec<_>>(), vec![1, 2, 4, 50, 3, 6, 7]);
+
+        // 経路上のノードだけが新しく、それ以外は共有している
+        assert!(!v1.ptr_eq(&v2));
+        assert!(!v1.left().unwrap().ptr_eq(&v2.left().unwrap()));
+        assert!(v1.right().unwrap().ptr_eq(&v2.right().unwrap()));
+        assert!(v1.get_at(&path(&[Side::Left, Side::Left])).unwrap()
+            .ptr_eq(&v2.get_at(&path(&[Side::Left, Side::Left])).unwrap()));
+
+        let v3 = v1.replace_at(&NodePath::root(), PersistentTree::new(0)).unwrap();
+        assert_eq!(v3.len(), 1);
+        assert_eq!(v1.replace_at(&path(&[Side::Left, Side::Left, Side::Left]), PersistentTree::new(0)).unwrap_err(), NodeError::NoNode);
+    }
+
+    #[test]
+    fn set_insert_remove() {
+        let v1 = create_test_tree();
+
+        let v2 = v1.set_at(&path(&[Side::Right]), 30).unwrap();
+        assert_eq!(v2.iter().copied().collect::<Vec<_>>(), vec![1, 2, 4, 5, 30, 6, 7]);
+        assert!(v1.get_at(&path(&[Side::Right, Side::Left])).unwrap()
+            .ptr_eq(&v2.get_at(&path(&[Side::Right, Side::Left])).unwrap()));
+
+        let v3 = v2.remove_at(&path(&[Side::Left])).unwrap();
+        assert_eq!(v3.iter().copied().collect::<Vec<_>>(), vec![1, 30, 6, 7]);
+        assert_eq!(v2.len(), 7);
+        assert_eq!(v3.remove_at(&NodePath::root()).unwrap_err(), NodeError::NoNode);
+        assert_eq!(v3.remove_at(&path(&[Side::Left])).unwrap_err(), NodeError::NoNode);
+
+        let v4 = v3.insert_at(&path(&[Side::Left]), PersistentTree::new(8)).unwrap();
+        assert_eq!(v4.iter().copied().collect::<Vec<_>>(), vec![1, 8, 30, 6, 7]);
+        assert_eq!(v4.insert_at(&path(&[Side::Left]), PersistentTree::new(9)).unwrap_err(), NodeError::ChildAddError);
+        assert_eq!(v4.insert_at(&path(&[Side::Left, Side::Left, Side::Left]), PersistentTree::new(9)).unwrap_err(), NodeError::NoNode);
+        assert_eq!(v3.len(), 4);
+    }
+
+    #[test]
+    fn convert() {
+        let mut root = Node::new(1);
+        root.create_left_node(2).unwrap().create_right_node(3).unwrap();
+
+        let tree = PersistentTree::from(&root);
+        assert_eq!(tree.get_at(&path(&[Side::Left, Side::Right])).unwrap().data(), &3);
+
+        let node = tree.set_at(&NodePath::root(), 10).unwrap().to_node();
+        assert_eq!(node.iter(&SearchOrder::PreOrder).copied().collect::<Vec<_>>(), vec![10, 2, 3]);
+    }
+
+    #[test]
+    fn deep_tree() {
+        let depth = 1_000_000;
+        let mut tree = PersistentTree::new(0i64);
+        for i in 1..depth {
+            tree = PersistentTree::with_children(i as i64, Some(tree), None);
+        }
+
+        let old = tree.clone();
+        let leaf: NodePath = (1..depth).map(|_| Side::Left).collect();
+        let tree = tree.set_at(&leaf, -1).unwrap();
+
+        assert_eq!(old.get_at(&leaf).unwrap().data(), &0);
+        assert_eq!(tree.get_at(&leaf).unwrap().data(), &-1);
+        assert_eq!(tree.to_node().len(), depth);
+    }
+}
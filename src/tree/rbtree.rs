@@ -0,0 +1,397 @@
+use std::cmp::Ordering;
+use std::mem;
+
+use super::Node;
+use super::ordered::{self, Entry, InvariantError, Link, MapIter, OrderedMap};
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Color {
+    Red,
+    Black,
+}
+
+type RbLink<K, V> = Link<K, V, Color>;
+type RbNode<K, V> = Node<Entry<K, V, Color>>;
+
+// 赤黒木(左傾赤黒木)　赤のリンクは必ず左の子に向ける
+// 親へのリンクを持たないNodeでも、上から下への再帰だけで挿入・削除の修正ができる
+#[derive(Debug)]
+pub struct RbTree<K, V> {
+    root: RbLink<K, V>,
+    len: usize,
+}
+
+impl<K: Ord, V> RbTree<K, V> {
+    pub fn new() -> Self {
+        RbTree {
+            root: None,
+            len: 0,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.root = None;
+        self.len = 0;
+    }
+
+    // 既にキーがある場合は値を置き換え、古い値を返す
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let ret = Self::insert_at(&mut self.root, key, value);
+        if ret.is_none() {
+            self.len += 1;
+        }
+
+        self.root.as_mut().unwrap().data.meta = Color::Black;
+        ret
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        ordered::get_mut(&mut self.root, key)
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        // 削除は「キーがある」ことを前提に赤のリンクを下へ運んでいくので、先に確かめておく
+        if !self.contains_key(key) {
+            return None;
+        }
+
+        let root = self.root.as_mut().unwrap();
+        if !Self::is_red(&root.left) && !Self::is_red(&root.right) {
+            root.data.meta = Color::Red;
+        }
+
+        let ret = Self::remove_at(&mut self.root, key);
+
+        if let Some(root) = self.root.as_mut() {
+            root.data.meta = Color::Black;
+        }
+        self.len -= 1;
+        Some(ret)
+    }
+
+    // キーの順序、要素数、根の色、赤の連続、黒の高さ、部分木の要素数を確かめる
+    pub fn check_invariants(&self) -> Result<(), InvariantError> {
+        ordered::check_order(self.root(), self.len)?;
+
+        if Self::is_red(&self.root) {
+            return Err( InvariantError::RootColor );
+        }
+        Self::check_node(self.root())?;
+        ordered::check_size(self.root())
+    }
+
+    // 部分木の黒の高さを返す
+    fn check_node(node: Option<&RbNode<K, V>>) -> Result<usize, InvariantError> {
+        match node {
+            None => Ok(1),
+            Some(n) => {
+                if n.data.meta == Color::Red && (Self::is_red(&n.left) || Self::is_red(&n.right)) {
+                    return Err( InvariantError::RedRed );
+                }
+
+                let left = Self::check_node(n.left())?;
+                let right = Self::check_node(n.right())?;
+
+                if left != right {
+                    return Err( InvariantError::BlackHeight );
+                }
+
+                match n.data.meta {
+                    Color::Black => Ok(left + 1),
+                    Color::Red => Ok(left),
+                }
+            }
+        }
+    }
+
+    fn insert_at(link: &mut RbLink<K, V>, key: K, value: V) -> Option<V> {
+        let node = match link {
+            Some(n) => n,
+            None => {
+                *link = Some(Box::new(Node::new(Entry::new(key, value, Color::Red))));
+                return None;
+            }
+        };
+
+        let ret = match key.cmp(&node.data.key) {
+            Ordering::Less => Self::insert_at(&mut node.left, key, value),
+            Ordering::Greater => Self::insert_at(&mut node.right, key, value),
+            Ordering::Equal => return Some(mem::replace(&mut node.data.value, value)),
+        };
+
+        Self::balance(node);
+        ret
+    }
+
+    // keyが部分木にあることが前提
+    fn remove_at(link: &mut RbLink<K, V>, key: &K) -> V {
+        let node = link.as_mut().unwrap();
+        let ret;
+
+        if key < &node.data.key {
+            if !Self::is_red(&node.left) && !Self::is_red_left(&node.left) {
+                Self::move_red_left(node);
+            }
+            ret = Self::remove_at(&mut node.left, key);
+        } else {
+            if Self::is_red(&node.left) {
+                Self::rotate_right(node);
+            }
+            if key == &node.data.key && node.right.is_none() {   // 左傾赤黒木では、右の子がなければ左の子もない
+                return link.take().unwrap().into_data().value;
+            }
+            if !Self::is_red(&node.right) && !Self::is_red_left(&node.right) {
+                Self::move_red_right(node);
+            }
+            if key == &node.data.key {
+                // 右の部分木の最小ノードとキー・値を入れ替えてから、最小ノードの方を消す
+                let mut min = Self::remove_min(&mut node.right);
+                mem::swap(&mut node.data.key, &mut min.data.key);
+                mem::swap(&mut node.data.value, &mut min.data.value);
+                ret = min.into_data().value;
+            } else {
+                ret = Self::remove_at(&mut node.right, key);
+            }
+        }
+
+        Self::balance(link.as_mut().unwrap());
+        ret
+    }
+
+    fn remove_min(link: &mut RbLink<K, V>) -> Box<RbNode<K, V>> {
+        let node = link.as_mut().unwrap();
+
+        if node.left.is_none() {
+            let mut min = link.take().unwrap();
+            *link = min.right.take();
+            return min;
+        }
+
+        if !Self::is_red(&node.left) && !Self::is_red_left(&node.left) {
+            Self::move_red_left(node);
+        }
+        let min = Self::remove_min(&mut node.left);
+
+        Self::balance(node);
+        min
+    }
+
+    fn is_red(link: &RbLink<K, V>) -> bool {
+        match link {
+            Some(n) => n.data.meta == Color::Red,
+            None => false,
+        }
+    }
+
+    // linkの左の子が赤か
+    fn is_red_left(link: &RbLink<K, V>) -> bool {
+        match link {
+            Some(n) => Self::is_red(&n.left),
+            None => false,
+        }
+    }
+
+    // 回転したあとも、部分木の根の色は元の根の色を引き継ぐ
+    fn rotate_left(node: &mut RbNode<K, V>) {
+        let color = node.data.meta;
+        node.rotate_left().unwrap();
+        node.data.meta = color;
+        node.left_mut().unwrap().data.meta = Color::Red;
+
+        ordered::update_size(node.left_mut().unwrap());
+        ordered::update_size(node);
+    }
+
+    fn rotate_right(node: &mut RbNode<K, V>) {
+        let color = node.data.meta;
+        node.rotate_right().unwrap();
+        node.data.meta = color;
+        node.right_mut().unwrap().data.meta = Color::Red;
+
+        ordered::update_size(node.right_mut().unwrap());
+        ordered::update_size(node);
+    }
+
+    fn flip_colors(node: &mut RbNode<K, V>) {
+        fn flip(c: &mut Color) {
+            *c = match c {
+                Color::Red => Color::Black,
+                Color::Black => Color::Red,
+            };
+        }
+
+        flip(&mut node.data.meta);
+        if let Some(l) = node.left_mut() {
+            flip(&mut l.data.meta);
+        }
+        if let Some(r) = node.right_mut() {
+            flip(&mut r.data.meta);
+        }
+    }
+
+    // nodeの左の子かその子を赤にする
+    fn move_red_left(node: &mut RbNode<K, V>) {
+        Self::flip_colors(node);
+        if Self::is_red_left(&node.right) {
+            Self::rotate_right(node.right_mut().unwrap());
+            Self::rotate_left(node);
+            Self::flip_colors(node);
+        }
+    }
+
+    // nodeの右の子かその子を赤にする
+    fn move_red_right(node: &mut RbNode<K, V>) {
+        Self::flip_colors(node);
+        if Self::is_red_left(&node.left) {
+            Self::rotate_right(node);
+            Self::flip_colors(node);
+        }
+    }
+
+    // 右に傾いた赤、連続した赤、両方の子が赤の状態を直し、要素数を計算し直す
+    fn balance(node: &mut RbNode<K, V>) {
+        if Self::is_red(&node.right) && !Self::is_red(&node.left) {
+            Self::rotate_left(node);
+        }
+        if Self::is_red(&node.left) && Self::is_red_left(&node.left) {
+            Self::rotate_right(node);
+        }
+        if Self::is_red(&node.left) && Self::is_red(&node.right) {
+            Self::flip_colors(node);
+        }
+        ordered::update_size(node);
+    }
+}
+
+impl<K: Ord, V> Default for RbTree<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord, V> OrderedMap<K, V> for RbTree<K, V> {
+    type Meta = Color;
+
+    fn root(&self) -> Option<&RbNode<K, V>> {
+        self.root.as_deref()
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+ordered::impl_map_into_iter!(RbTree, Color);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::SearchOrder;
+    use crate::tree::test_util;
+
+    #[test]
+    fn insert_get() {
+        let mut tree = RbTree::new();
+
+        for k in &[5, 3, 8, 1, 4, 7, 9, 2, 6] {
+            assert_eq!(tree.insert(*k, k * 10), None);
+            tree.check_invariants().unwrap();
+        }
+        assert_eq!(tree.insert(4, 0), Some(40));
+        assert_eq!(tree.len(), 9);
+        assert_eq!(tree.get(&4), Some(&0));
+        assert_eq!(tree.get(&10), None);
+        assert_eq!(tree.min(), Some((&1, &10)));
+        assert_eq!(tree.max(), Some((&9, &90)));
+
+        let keys: Vec<i32> = tree.range(3..7).map(|(k, _)| *k).collect();
+        assert_eq!(keys, vec![3, 4, 5, 6]);
+        assert_eq!(tree.root().unwrap().as_ref().meta(), &Color::Black);
+    }
+
+    #[test]
+    fn sorted_insert_and_remove() {
+        let mut tree = RbTree::new();
+
+        for k in 0..1000 {
+            tree.insert(k, ());
+        }
+        tree.check_invariants().unwrap();
+
+        // 赤黒木の高さは 2 log2(n + 1) 以下
+        let height = tree.root().unwrap().levels().len();
+        assert!(height <= 20);
+
+        for k in (0..1000).rev().filter(|k| k % 2 == 0) {
+            assert_eq!(tree.remove(&k), Some(()));
+            tree.check_invariants().unwrap();
+        }
+        assert_eq!(tree.remove(&0), None);
+
+        let keys: Vec<i32> = tree.iter().map(|(k, _)| *k).take(3).collect();
+        assert_eq!(keys, vec![1, 3, 5]);
+        assert_eq!(tree.len(), 500);
+    }
+
+    #[test]
+    fn check_invariants_detects_errors() {
+        let mut tree = RbTree::new();
+        for k in 0..7 {
+            tree.insert(k, ());
+        }
+        tree.check_invariants().unwrap();
+
+        // 根の左の子を赤にすると、黒の高さがずれる
+        tree.root.as_mut().unwrap().left_mut().unwrap().data.meta = Color::Red;
+        assert_eq!(tree.check_invariants(), Err(InvariantError::BlackHeight));
+
+        tree.root.as_mut().unwrap().data.meta = Color::Red;
+        assert_eq!(tree.check_invariants(), Err(InvariantError::RootColor));
+
+        let mut tree = RbTree::new();
+        for k in 0..3 {
+            tree.insert(k, ());
+        }
+        for e in tree.root.as_mut().unwrap().iter_mut(&SearchOrder::PreOrder).skip(1) {
+            e.meta = Color::Red;
+        }
+        tree.root.as_mut().unwrap().left_mut().unwrap().create_left_node(Entry::new(-1, (), Color::Red)).unwrap();
+        tree.len += 1;
+        assert_eq!(tree.check_invariants(), Err(InvariantError::RedRed));
+    }
+
+    #[test]
+    fn compare_with_btreemap() {
+        test_util::compare_with_btreemap(&mut RbTree::new(), 11);
+    }
+
+    #[test]
+    fn order_statistics() {
+        let mut tree = RbTree::new();
+        assert_eq!(tree.median(), None);
+
+        // 0, 5, 10, ..., 495 を逆順に入れる
+        for k in (0..100).rev() {
+            tree.insert(k * 5, ());
+        }
+        tree.check_invariants().unwrap();
+
+        assert_eq!(tree.select(17), Some((&85, &())));
+        assert_eq!(tree.rank(&85), 17);
+        assert_eq!(tree.rank(&86), 18);
+        assert_eq!(tree.median(), Some((&245, &())));
+        assert_eq!(tree.percentile(25.0), Some((&120, &())));
+        assert_eq!(tree.percentile(99.5), Some((&495, &())));
+
+        for k in (0..50).map(|k| k * 5) {
+            tree.remove(&k);
+        }
+        tree.check_invariants().unwrap();
+
+        assert_eq!(tree.select(0), Some((&250, &())));
+        assert_eq!(tree.rank(&0), 0);
+        assert_eq!(tree.rank(&300), 10);
+        assert_eq!(tree.median(), Some((&370, &())));
+        assert_eq!(tree.percentile(101.0), None);
+    }
+}
@@ -0,0 +1,337 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::hash::Hash;
+
+use super::Node;
+
+// 各ノードの(左の子, 右の子)の位置
+pub(super) type Children = Vec<(Option<usize>, Option<usize>)>;
+
+// 走査順の列から木を作り直せなかった理由　位置はすべて0始まりの添字
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum RebuildError {
+    Empty,                          // 列が空
+    LengthMismatch(usize, usize),   // 2つの列の長さが違う
+    Duplicate(usize),               // 通りがけ順の列のこの位置の要素が、それより前にも出てきている
+    Inconsistent(usize),            // 行きがけ順(帰りがけ順)の列のこの位置の要素が、通りがけ順で入るべき範囲にない
+    RootHole,                       // レベル順(配列レイアウト)の列の先頭が空
+    Orphan(usize),                  // レベル順(配列レイアウト)の列のこの位置の要素には、親になるノードがない
+    TooDeep(usize),                 // 配列レイアウトにすると長くなりすぎる　この深さのノードで上限を超えた
+}
+
+impl fmt::Display for RebuildError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RebuildError::Empty => write!(f, "sequence is empty"),
+            RebuildError::LengthMismatch(a, b) => write!(f, "sequence lengths differ: {} and {}", a, b),
+            RebuildError::Duplicate(i) => write!(f, "in-order element at {} appears more than once", i),
+            RebuildError::Inconsistent(i) => write!(f, "element at {} does not fit the in-order sequence", i),
+            RebuildError::RootHole => write!(f, "level-order sequence starts with a hole"),
+            RebuildError::Orphan(i) => write!(f, "level-order element at {} has no parent", i),
+            RebuildError::TooDeep(d) => write!(f, "node at depth {} is too deep for array layout", d),
+        }
+    }
+}
+
+impl<T: Clone + Eq + Hash> Node<T> {
+    // 行きがけ順と通りがけ順から作る　要素はすべて異なっている必要がある
+    pub fn from_preorder_inorder(pre: &[T], inorder: &[T]) -> Result<Node<T>, RebuildError> {
+        let children = split_by_inorder(pre, inorder, false)?;
+        // 行きがけ順では子は親より後ろにあるので、後ろから組み立てる
+        Ok(assemble(pre.to_vec(), &children, (0..pre.len()).rev()))
+    }
+
+    // 帰りがけ順と通りがけ順から作る　要素はすべて異なっている必要がある
+    pub fn from_postorder_inorder(post: &[T], inorder: &[T]) -> Result<Node<T>, RebuildError> {
+        let children = split_by_inorder(post, inorder, true)?;
+        // 帰りがけ順では子は親より前にあるので、前から組み立てる
+        Ok(assemble(post.to_vec(), &children, 0..post.len()))
+    }
+}
+
+impl<T: Clone> Node<T> {
+    // レベル順に並べ、ない子をNoneで埋めた列から作る
+    // 各ノードの子は、列の後ろの方に(左, 右)の順で現れる　Noneの子は列に含めない
+    // 末尾のNoneは省略してよい
+    pub fn from_level_order(seq: &[Option<T>]) -> Result<Node<T>, RebuildError> {
+        match seq.first() {
+            None => return Err( RebuildError::Empty ),
+            Some(None) => return Err( RebuildError::RootHole ),
+            Some(Some(_)) => (),
+        }
+
+        let mut children = vec![(None, None); seq.len()];
+        let mut parents = VecDeque::new();
+        parents.push_back(0);
+
+        let mut idx = 1;
+        while idx < seq.len() {
+            // 親が尽きても、残りがすべてNoneなら省略してよい末尾の穴として読み飛ばす
+            let parent = match parents.pop_front() {
+                Some(p) => p,
+                None => match seq[idx..].iter().position(|x| x.is_some()) {
+                    Some(pos) => return Err( RebuildError::Orphan(idx + pos) ),
+                    None => break,
+                },
+            };
+
+            for slot in 0..2 {
+                if idx >= seq.len() {
+                    break;
+                }
+                if seq[idx].is_some() {
+                    if slot == 0 {
+                        children[parent].0 = Some(idx);
+                    } else {
+                        children[parent].1 = Some(idx);
+                    }
+                    parents.push_back(idx);
+                }
+                idx += 1;
+            }
+        }
+
+        let data: Vec<T> = seq.iter().flatten().cloned().collect();
+        // Noneを除いた位置に詰め直す
+        let mut packed = vec![0; seq.len()];
+        let mut cnt = 0;
+        for (i, x) in seq.iter().enumerate() {
+            if x.is_some() {
+                packed[i] = cnt;
+                cnt += 1;
+            }
+        }
+        let children: Children = children.iter().enumerate()
+            .filter(|(i, _)| seq[*i].is_some())
+            .map(|(_, (l, r))| (l.map(|l| packed[l]), r.map(|r| packed[r])))
+            .collect();
+
+        let len = data.len();
+        Ok(assemble(data, &children, (0..len).rev()))
+    }
+
+    // from_level_orderで読める列を作る　末尾のNoneは省く
+    pub fn to_level_order(&self) -> Vec<Option<T>> {
+        let mut seq = Vec::new();
+        let mut queue = VecDeque::new();
+        queue.push_back(Some(self));
+
+        while let Some(slot) = queue.pop_front() {
+            match slot {
+                Some(node) => {
+                    seq.push(Some(node.data.clone()));
+                    queue.push_back(node.left());
+                    queue.push_back(node.right());
+                },
+                None => seq.push(None),
+            }
+        }
+
+        while let Some(None) = seq.last() {
+            seq.pop();
+        }
+        seq
+    }
+}
+
+// orderの各要素について、(左の子, 右の子)のorder上の位置を求める
+// postがtrueならorderは帰りがけ順、falseなら行きがけ順
+fn split_by_inorder<T: Eq + Hash>(order: &[T], inorder: &[T], post: bool) -> Result<Children, RebuildError> {
+    if order.len() != inorder.len() {
+        return Err( RebuildError::LengthMismatch(order.len(), inorder.len()) );
+    }
+    if order.is_empty() {
+        return Err( RebuildError::Empty );
+    }
+
+    let mut pos = HashMap::new();
+    for (i, x) in inorder.iter().enumerate() {
+        if pos.insert(x, i).is_some() {
+            return Err( RebuildError::Duplicate(i) );
+        }
+    }
+
+    let mut children = vec![(None, None); order.len()];
+    // (部分木のorder上の開始位置, 通りがけ順上の開始位置, 要素数)
+    let mut stack = vec![(0, 0, order.len())];
+
+    while let Some((start, in_start, len)) = stack.pop() {
+        let root = if post { start + len - 1 } else { start };
+        let k = match pos.get(&order[root]) {
+            Some(&k) if in_start <= k && k < in_start + len => k,
+            _ => return Err( RebuildError::Inconsistent(root) ),
+        };
+
+        let left_len = k - in_start;
+        let right_len = len - left_len - 1;
+        let (left_start, right_start) = if post {
+            (start, start + left_len)
+        } else {
+            (start + 1, start + 1 + left_len)
+        };
+
+        // 左の部分木から先に調べる
+        if right_len > 0 {
+            let r = if post { right_start + right_len - 1 } else { right_start };
+            children[root].1 = Some(r);
+            stack.push((right_start, k + 1, right_len));
+        }
+        if left_len > 0 {
+            let l = if post { left_start + left_len - 1 } else { left_start };
+            children[root].0 = Some(l);
+            stack.push((left_start, in_start, left_len));
+        }
+    }
+
+    Ok(children)
+}
+
+// 子が必ず先に組み上がる順序builtで各ノードを作り、最後に作ったものを根として返す
+pub(super) fn assemble<T, I>(data: Vec<T>, children: &[(Option<usize>, Option<usize>)], built: I) -> Node<T>
+where I: Iterator<Item = usize> {
+    let mut data: Vec<Option<T>> = data.into_iter().map(Some).collect();
+    let mut slots: Vec<Option<Node<T>>> = (0..data.len()).map(|_| None).collect();
+    let mut last = 0;
+
+    for i in built {
+        let (l, r) = children[i];
+        slots[i] = Some(Node {
+            data: data[i].take().unwrap(),
+            left: l.and_then(|l| slots[l].take()).map(Box::new).into(),
+            right: r.and_then(|r| slots[r].take()).map(Box::new).into(),
+        });
+        last = i;
+    }
+
+    slots[last].take().unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::SearchOrder;
+    use crate::tree::test_util::{create_test_tree, lcg};
+
+    fn collect(root: &Node<i32>, order: SearchOrder) -> Vec<i32> {
+        root.iter(&order).copied().collect()
+    }
+
+    // 形がでたらめな木　要素は行きがけ順に0, 1, 2, ...
+    fn create_random_tree(n: i32, seed: &mut u64) -> Node<i32> {
+        let mut root = Node::new(0);
+        for i in 1..n {
+            let mut node = &mut root;
+            loop {
+                let go_left = lcg(seed) % 2 == 1;
+                let next = if go_left { &mut node.left } else { &mut node.right };
+                if next.is_none() {
+                    **next = Some(Box::new(Node::new(i)));
+                    break;
+                }
+                node = next.as_mut().unwrap();
+            }
+        }
+        root
+    }
+
+    #[test]
+    fn pre_in_round_trip() {
+        let root = create_test_tree();
+        let pre = collect(&root, SearchOrder::PreOrder);
+        let ino = collect(&root, SearchOrder::InOrder);
+        let post = collect(&root, SearchOrder::PostOrder);
+
+        let a = Node::from_preorder_inorder(&pre, &ino).unwrap();
+        assert_eq!(collect(&a, SearchOrder::PostOrder), post);
+
+        let b = Node::from_postorder_inorder(&post, &ino).unwrap();
+        assert_eq!(collect(&b, SearchOrder::PreOrder), pre);
+
+        let mut seed = 7;
+        for n in 1..40 {
+            let root = create_random_tree(n, &mut seed);
+            let pre = collect(&root, SearchOrder::PreOrder);
+            let ino = collect(&root, SearchOrder::InOrder);
+            let post = collect(&root, SearchOrder::PostOrder);
+
+            let a = Node::from_preorder_inorder(&pre, &ino).unwrap();
+            assert_eq!(collect(&a, SearchOrder::PostOrder), post);
+            let b = Node::from_postorder_inorder(&post, &ino).unwrap();
+            assert_eq!(collect(&b, SearchOrder::PreOrder), pre);
+            assert_eq!(collect(&b, SearchOrder::LevelOrder), collect(&root, SearchOrder::LevelOrder));
+        }
+    }
+
+    #[test]
+    fn pre_in_errors() {
+        assert_eq!(Node::<i32>::from_preorder_inorder(&[], &[]).unwrap_err(), RebuildError::Empty);
+        assert_eq!(Node::from_preorder_inorder(&[1, 2], &[1]).unwrap_err(), RebuildError::LengthMismatch(2, 1));
+        assert_eq!(Node::from_preorder_inorder(&[1, 2, 3], &[2, 1, 2]).unwrap_err(), RebuildError::Duplicate(2));
+        // 3は通りがけ順にない
+        assert_eq!(Node::from_preorder_inorder(&[1, 3, 2], &[2, 1, 4]).unwrap_err(), RebuildError::Inconsistent(1));
+        // 3は1の右にあるはずなのに、行きがけ順では左の部分木の位置にある
+        assert_eq!(Node::from_preorder_inorder(&[1, 3, 2], &[2, 1, 3]).unwrap_err(), RebuildError::Inconsistent(1));
+        assert_eq!(Node::from_postorder_inorder(&[3, 2, 1], &[2, 1, 3]).unwrap_err(), RebuildError::Inconsistent(0));
+        assert_eq!(RebuildError::Inconsistent(2).to_string(), "element at 2 does not fit the in-order sequence");
+    }
+
+    #[test]
+    fn level_order() {
+        let root = create_test_tree();
+        let seq = root.to_level_order();
+        assert_eq!(seq, vec![Some(1), Some(2), Some(3), Some(4), Some(5), Some(6), Some(7)]);
+        let a = Node::from_level_order(&seq).unwrap();
+        assert_eq!(collect(&a, SearchOrder::PreOrder), collect(&root, SearchOrder::PreOrder));
+
+        //     1
+        //    / \
+        //   2   3
+        //    \   \
+        //     4   5
+        //    /
+        //   6
+        let seq = vec![Some(1), Some(2), Some(3), None, Some(4), None, Some(5), Some(6)];
+        let a = Node::from_level_order(&seq).unwrap();
+        assert_eq!(collect(&a, SearchOrder::PreOrder), vec![1, 2, 4, 6, 3, 5]);
+        assert_eq!(collect(&a, SearchOrder::InOrder), vec![2, 6, 4, 1, 3, 5]);
+        assert_eq!(a.to_level_order(), seq);
+
+        let mut seed = 11;
+        for n in 1..40 {
+            let root = create_random_tree(n, &mut seed);
+            let a = Node::from_level_order(&root.to_level_order()).unwrap();
+            assert_eq!(collect(&a, SearchOrder::PreOrder), collect(&root, SearchOrder::PreOrder));
+            assert_eq!(collect(&a, SearchOrder::InOrder), collect(&root, SearchOrder::InOrder));
+        }
+    }
+
+    #[test]
+    fn level_order_errors() {
+        assert_eq!(Node::<i32>::from_level_order(&[]).unwrap_err(), RebuildError::Empty);
+        assert_eq!(Node::from_level_order(&[None, Some(1)]).unwrap_err(), RebuildError::RootHole);
+        // 1の子は2つとも空なので、3つ目の要素には親がない
+        assert_eq!(Node::from_level_order(&[Some(1), None, None, Some(2)]).unwrap_err(), RebuildError::Orphan(3));
+        assert_eq!(Node::from_level_order(&[Some(1), None, None, None, Some(2)]).unwrap_err(), RebuildError::Orphan(4));
+
+        // 親のない位置でも、末尾のNoneは省略したものとして読める
+        let root = Node::from_level_order(&[Some(1), None, None, None]).unwrap();
+        assert!(root.is_leaf());
+        let root = Node::from_level_order(&[Some(1), Some(2), None, None, None, None, None]).unwrap();
+        assert_eq!(root.to_level_order(), vec![Some(1), Some(2)]);
+    }
+
+    #[test]
+    fn deep_tree() {
+        let depth = 100_000;
+        let pre: Vec<i32> = (0..depth).collect();
+        let ino: Vec<i32> = (0..depth).rev().collect();
+
+        let root = Node::from_preorder_inorder(&pre, &ino).unwrap();
+        assert_eq!(root.height(), depth as usize);
+
+        let post: Vec<i32> = (0..depth).rev().collect();
+        let root = Node::from_postorder_inorder(&post, &ino).unwrap();
+        assert_eq!(root.as_ref(), &0);
+        assert_eq!(root.height(), depth as usize);
+    }
+}
@@ -0,0 +1,295 @@
+use std::fmt::Display;
+
+use super::Node;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum TextLayout {
+    Sideways,   // 根を左上に置き、子を下の行に字下げして並べる(treeコマンド風)
+    TopDown,    // 根を上に置き、子をその下に左右に並べる
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct TextStyle {
+    pub layout: TextLayout,
+    pub ascii: bool,        // trueなら罫線文字を使わずASCII文字だけで描く
+}
+
+impl Default for TextStyle {
+    fn default() -> Self {
+        TextStyle {
+            layout: TextLayout::Sideways,
+            ascii: false,
+        }
+    }
+}
+
+// 罫線の部品
+struct Glyphs {
+    branch: &'static str,   // Sideways: 後ろに兄弟がある子
+    last: &'static str,     // Sideways: 最後の子
+    pipe: &'static str,     // Sideways: 縦線
+    space: &'static str,
+    empty: &'static str,    // Sideways: 片方しかない子のもう片方
+    horizontal: char,       // TopDown
+    down_left: char,
+    down_right: char,
+    up_left: char,
+    up_right: char,
+    up_both: char,
+}
+
+const UNICODE: Glyphs = Glyphs {
+    branch: "├── ",
+    last: "└── ",
+    pipe: "│   ",
+    space: "    ",
+    empty: "∅",
+    horizontal: '─',
+    down_left: '┌',
+    down_right: '┐',
+    up_left: '┘',
+    up_right: '└',
+    up_both: '┴',
+};
+
+const ASCII: Glyphs = Glyphs {
+    branch: "+-- ",
+    last: "`-- ",
+    pipe: "|   ",
+    space: "    ",
+    empty: "-",
+    horizontal: '-',
+    down_left: '+',
+    down_right: '+',
+    up_left: '+',
+    up_right: '+',
+    up_both: '+',
+};
+
+// TopDownで組み立て中の部分木の絵　centerは根のラベルの中心の列
+struct Block {
+    lines: Vec<String>,
+    width: usize,
+    center: usize,
+}
+
+impl<T> Node<T> {
+    // 罫線文字で木を横向きに描く
+    pub fn render_text(&self) -> String
+    where T: Display {
+        self.render_text_with(&TextStyle::default(), |x| x.to_string())
+    }
+
+    pub fn render_text_with<F>(&self, style: &TextStyle, mut format: F) -> String
+    where F: FnMut(&T) -> String {
+        let glyphs = if style.ascii { &ASCII } else { &UNICODE };
+
+        let lines = match style.layout {
+            TextLayout::Sideways => Self::render_sideways(self, glyphs, &mut format),
+            TextLayout::TopDown => Self::render_top_down(self, glyphs, &mut format),
+        };
+
+        let mut out = String::new();
+        for line in lines.iter() {
+            out.push_str(line.trim_end());
+            out.push('\n');
+        }
+        out
+    }
+
+    fn render_sideways<F>(root: &Node<T>, glyphs: &Glyphs, format: &mut F) -> Vec<String>
+    where F: FnMut(&T) -> String {
+        let mut lines = Vec::new();
+        // (ノード, この行の字下げ, 子の行の字下げ)　子が片方だけのときは、もう片方をNoneとして描く
+        let mut stack: Vec<(Option<&Node<T>>, String, String)> = vec![(Some(root), String::new(), String::new())];
+
+        while let Some((node, head, indent)) = stack.pop() {
+            let node = match node {
+                Some(n) => n,
+                None => {
+                    lines.push(head + glyphs.empty);
+                    continue;
+                }
+            };
+
+            lines.push(head + &format(&node.data));
+
+            if node.left.is_none() && node.right.is_none() {
+                continue;
+            }
+
+            // 左の子を先に描くので、右の子から積む
+            stack.push((node.right(), indent.clone() + glyphs.last, indent.clone() + glyphs.space));
+            stack.push((node.left(), indent.clone() + glyphs.branch, indent + glyphs.pipe));
+        }
+
+        lines
+    }
+
+    fn render_top_down<F>(root: &Node<T>, glyphs: &Glyphs, format: &mut F) -> Vec<String>
+    where F: FnMut(&T) -> String {
+        // 帰りがけ順に部分木の絵を作る　親を描くときには子の絵がdoneの末尾に左→右の順で並んでいる
+        let mut stack = vec![(root, false)];
+        let mut done: Vec<Block> = Vec::new();
+
+        while let Some((node, expanded)) = stack.pop() {
+            if !expanded {
+                stack.push((node, true));
+                if let Some(r) = node.right() {
+                    stack.push((r, false));
+                }
+                if let Some(l) = node.left() {
+                    stack.push((l, false));
+                }
+                continue;
+            }
+
+            let right = node.right.as_ref().map(|_| done.pop().unwrap());
+            let left = node.left.as_ref().map(|_| done.pop().unwrap());
+
+            done.push(Self::join_blocks(format(&node.data), left, right, glyphs));
+        }
+
+        done.pop().unwrap().lines
+    }
+
+    fn join_blocks(label: String, left: Option<Block>, right: Option<Block>, glyphs: &Glyphs) -> Block {
+        const GAP: usize = 3;   // 左右の部分木の間の空白
+
+        let label_width = label.chars().count();
+
+        if left.is_none() && right.is_none() {
+            return Block {
+                lines: vec![label],
+                width: label_width,
+                center: label_width / 2,
+            };
+        }
+
+        let left_width = left.as_ref().map_or(0, |b| b.width);
+        let right_x = if left.is_some() { left_width + GAP } else { 0 };
+
+        // 親のラベルの中心の位置　片方の子しかないときは、そちら側に2文字ずらす
+        let lc = left.as_ref().map(|b| b.center as isize);
+        let rc = right.as_ref().map(|b| (right_x + b.center) as isize);
+        let pc = match (lc, rc) {
+            (Some(l), Some(r)) => (l + r) / 2,
+            (Some(l), None) => l + 2,
+            (None, Some(r)) => r - 2,
+            (None, None) => unreachable!(),
+        };
+
+        let label_x = pc - (label_width / 2) as isize;
+        let offset = -label_x.min(0);       // ラベルが左にはみ出す場合は全体を右にずらす
+        let children_width = right_x + right.as_ref().map_or(0, |b| b.width);
+        let width = (children_width as isize + offset).max(label_x + offset + label_width as isize) as usize;
+
+        let mut lines = Vec::new();
+        lines.push(" ".repeat((label_x + offset) as usize) + &label);
+
+        let mut connector = vec![' '; width];
+        let at = |x: isize| (x + offset) as usize;
+        if let Some(l) = lc {
+            connector[at(l)] = glyphs.down_left;
+            for c in connector.iter_mut().take(at(pc)).skip(at(l) + 1) {
+                *c = glyphs.horizontal;
+            }
+            connector[at(pc)] = glyphs.up_left;
+        }
+        if let Some(r) = rc {
+            connector[at(r)] = glyphs.down_right;
+            for c in connector.iter_mut().take(at(r)).skip(at(pc) + 1) {
+                *c = glyphs.horizontal;
+            }
+            connector[at(pc)] = if lc.is_some() { glyphs.up_both } else { glyphs.up_right };
+        }
+        lines.push(connector.into_iter().collect());
+
+        let left_lines = left.as_ref().map_or(0, |b| b.lines.len());
+        let right_lines = right.as_ref().map_or(0, |b| b.lines.len());
+
+        for i in 0..left_lines.max(right_lines) {
+            let mut line = " ".repeat(offset as usize);
+
+            if let Some(b) = &left {
+                let s = b.lines.get(i).map_or("", |s| s.as_str());
+                line.push_str(s);
+                line.push_str(&" ".repeat(left_width - s.chars().count() + GAP));
+            }
+            if let Some(b) = &right {
+                line.push_str(b.lines.get(i).map_or("", |s| s.as_str()));
+            }
+            lines.push(line);
+        }
+
+        Block {
+            lines,
+            width,
+            center: at(pc),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::test_util::create_test_tree;
+
+    #[test]
+    fn sideways() {
+        let mut root = create_test_tree();
+        root.right_mut().unwrap().take_left();
+
+        let expected = "\
+1
+├── 2
+│   ├── 4
+│   └── 5
+└── 3
+    ├── ∅
+    └── 7
+";
+        assert_eq!(root.render_text(), expected);
+
+        let style = TextStyle { layout: TextLayout::Sideways, ascii: true };
+        let expected = "\
+<1>
++-- <2>
+|   +-- <4>
+|   `-- <5>
+`-- <3>
+    +-- -
+    `-- <7>
+";
+        assert_eq!(root.render_text_with(&style, |x| format!("<{}>", x)), expected);
+    }
+
+    #[test]
+    fn top_down() {
+        let root = create_test_tree();
+        let style = TextStyle { layout: TextLayout::TopDown, ascii: false };
+
+        let expected = concat!(
+            "      1\n",
+            "  ┌───┴───┐\n",
+            "  2       3\n",
+            "┌─┴─┐   ┌─┴─┐\n",
+            "4   5   6   7\n",
+        );
+        assert_eq!(root.render_text_with(&style, |x| x.to_string()), expected);
+
+        // 片側だけの子、幅の広いラベル
+        let mut root = Node::new("root");
+        root.create_left_node("l").unwrap().create_right_node("lr").unwrap();
+
+        let style = TextStyle { layout: TextLayout::TopDown, ascii: true };
+        let expected = concat!(
+            "root\n",
+            "+-+\n",
+            "l\n",
+            "+-+\n",
+            " lr\n",
+        );
+        assert_eq!(root.render_text_with(&style, |x| x.to_string()), expected);
+    }
+}
@@ -0,0 +1,240 @@
+use std::cmp::{self, Ordering};
+use std::ops::{Add, Bound, RangeBounds};
+
+use super::Node;
+
+// セグメント木のノード　[start, end)の区間の和と最小値を持つ
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segment<T> {
+    pub start: usize,
+    pub end: usize,
+    pub sum: T,
+    pub min: T,
+}
+
+// 配列の区間の和・最小値を O(log n) で求め、要素を O(log n) で書き換えられる
+// 葉が配列の各要素、内部ノードが子の区間をつなげた区間を受け持つ
+// 最小値はPartialOrdで比べるのでf64も使える　NaNと比べたときは先に見た方を残す
+#[derive(Debug)]
+pub struct SegmentTree<T> {
+    root: Option<Node<Segment<T>>>,
+    len: usize,
+}
+
+impl<T> SegmentTree<T>
+where T: Copy + PartialOrd + Add<Output = T> {
+    pub fn new(values: &[T]) -> Self {
+        SegmentTree {
+            root: if values.is_empty() { None } else { Some(Self::build(values, 0, values.len())) },
+            len: values.len(),
+        }
+    }
+
+    // 高さはlog nなので再帰で組み立てる
+    fn build(values: &[T], start: usize, end: usize) -> Node<Segment<T>> {
+        if end - start == 1 {
+            let x = values[start];
+            return Node::new(Segment { start, end, sum: x, min: x });
+        }
+
+        let mid = start + (end - start) / 2;
+        let left = Self::build(values, start, mid);
+        let right = Self::build(values, mid, end);
+
+        let mut node = Node::new(Self::merge(&left.data, &right.data));
+        node.replace_left(left);
+        node.replace_right(right);
+        node
+    }
+
+    fn merge(l: &Segment<T>, r: &Segment<T>) -> Segment<T> {
+        Segment {
+            start: l.start,
+            end: r.end,
+            sum: l.sum + r.sum,
+            min: Self::min_of(l.min, r.min),
+        }
+    }
+
+    fn min_of(a: T, b: T) -> T {
+        match b.partial_cmp(&a) {
+            Some(Ordering::Less) => b,
+            _ => a,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn root(&self) -> Option<&Node<Segment<T>>> {
+        self.root.as_ref()
+    }
+
+    pub fn get(&self, idx: usize) -> Option<T> {
+        self.sum(idx..=idx)
+    }
+
+    // idx番目の要素を書き換え、根までの集約値を更新する　範囲外ならfalse
+    pub fn update(&mut self, idx: usize, value: T) -> bool {
+        if idx >= self.len {
+            return false;
+        }
+
+        Self::update_at(self.root.as_mut().unwrap(), idx, value);
+        true
+    }
+
+    // 葉まで降りて書き換え、戻りながら集約し直す
+    fn update_at(node: &mut Node<Segment<T>>, idx: usize, value: T) {
+        if node.is_leaf() {
+            node.data.sum = value;
+            node.data.min = value;
+            return;
+        }
+
+        let Node { data, left, right } = node;
+        let (left, right) = (left.as_mut().unwrap(), right.as_mut().unwrap());
+        if idx < left.data.end {
+            Self::update_at(left, idx, value);
+        } else {
+            Self::update_at(right, idx, value);
+        }
+        *data = Self::merge(&left.data, &right.data);
+    }
+
+    // 範囲の和　空の範囲ならNone
+    pub fn sum<R: RangeBounds<usize>>(&self, range: R) -> Option<T> {
+        self.query(range).map(|(sum, _)| sum)
+    }
+
+    // 範囲の最小値　空の範囲ならNone
+    pub fn min<R: RangeBounds<usize>>(&self, range: R) -> Option<T> {
+        self.query(range).map(|(_, min)| min)
+    }
+
+    // usize::MAXを含む範囲でも溢れないようにする　終わりはどうせlenで切り詰める
+    fn query<R: RangeBounds<usize>>(&self, range: R) -> Option<(T, T)> {
+        let start = match range.start_bound() {
+            Bound::Included(s) => *s,
+            Bound::Excluded(s) => s.checked_add(1)?,
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(e) => e.saturating_add(1),
+            Bound::Excluded(e) => *e,
+            Bound::Unbounded => self.len,
+        };
+        let end = cmp::min(end, self.len);
+        if start >= end {
+            return None;
+        }
+
+        // 範囲にすっぽり入るノードだけを集める
+        let mut result: Option<(T, T)> = None;
+        let mut stack = vec![self.root.as_ref()?];
+        while let Some(node) = stack.pop() {
+            let seg = &node.data;
+            if seg.end <= start || end <= seg.start {
+                continue;
+            }
+            if start <= seg.start && seg.end <= end {
+                result = Some(match result {
+                    Some((sum, min)) => (sum + seg.sum, Self::min_of(min, seg.min)),
+                    None => (seg.sum, seg.min),
+                });
+                continue;
+            }
+            stack.extend(node.right());
+            stack.extend(node.left());
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::test_util::lcg;
+
+    #[test]
+    fn query() {
+        let tree = SegmentTree::new(&[5, 3, 8, 6, 1, 4, 7]);
+
+        assert_eq!(tree.len(), 7);
+        assert_eq!(tree.sum(..), Some(34));
+        assert_eq!(tree.min(..), Some(1));
+        assert_eq!(tree.sum(1..4), Some(17));
+        assert_eq!(tree.min(1..4), Some(3));
+        assert_eq!(tree.min(2..=3), Some(6));
+        assert_eq!(tree.sum(5..100), Some(11));
+        assert_eq!(tree.sum(3..3), None);
+        assert_eq!(tree.get(4), Some(1));
+        assert_eq!(tree.get(7), None);
+        assert_eq!(tree.root().unwrap().height(), 4);
+
+        let empty: SegmentTree<i32> = SegmentTree::new(&[]);
+        assert!(empty.is_empty());
+        assert_eq!(empty.sum(..), None);
+    }
+
+    #[test]
+    fn extreme_bounds() {
+        let tree = SegmentTree::new(&[5, 3, 8]);
+
+        assert_eq!(tree.get(usize::MAX), None);
+        assert_eq!(tree.sum(..=usize::MAX), Some(16));
+        assert_eq!(tree.sum(1..=usize::MAX), Some(11));
+        assert_eq!(tree.min((Bound::Excluded(usize::MAX), Bound::Unbounded)), None);
+        assert_eq!(tree.min((Bound::Excluded(0), Bound::Included(usize::MAX))), Some(3));
+        assert_eq!(tree.sum(usize::MAX..), None);
+    }
+
+    #[test]
+    fn float_values() {
+        let mut tree = SegmentTree::new(&[1.5, -0.5, 2.0, 0.25]);
+
+        assert_eq!(tree.sum(..), Some(3.25));
+        assert_eq!(tree.min(..), Some(-0.5));
+        assert_eq!(tree.min(2..), Some(0.25));
+
+        tree.update(1, 4.0);
+        assert_eq!(tree.min(..), Some(0.25));
+    }
+
+    #[test]
+    fn point_update() {
+        let mut tree = SegmentTree::new(&[5, 3, 8, 6, 1, 4, 7]);
+
+        assert!(tree.update(4, 10));
+        assert_eq!(tree.min(..), Some(3));
+        assert_eq!(tree.sum(..), Some(43));
+        assert_eq!(tree.get(4), Some(10));
+        assert!(!tree.update(7, 0));
+    }
+
+    #[test]
+    fn compare_with_slice() {
+        let mut seed = 9;
+        let mut values: Vec<i64> = (0..200).map(|_| (lcg(&mut seed) % 1000) as i64 - 500).collect();
+        let mut tree = SegmentTree::new(&values);
+
+        for _ in 0..2000 {
+            if lcg(&mut seed) % 3 == 1 {
+                let idx = (lcg(&mut seed) % 200) as usize;
+                let x = (lcg(&mut seed) % 1000) as i64 - 500;
+                tree.update(idx, x);
+                values[idx] = x;
+            } else {
+                let a = (lcg(&mut seed) % 200) as usize;
+                let b = a + (lcg(&mut seed) % (200 - a as u64)) as usize + 1;
+                assert_eq!(tree.sum(a..b), Some(values[a..b].iter().sum()));
+                assert_eq!(tree.min(a..b), values[a..b].iter().min().copied());
+            }
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use serde::de::Error;
+use serde::ser::SerializeSeq;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::rebuild::{assemble, Children};
+use super::{Node, Side};
+
+// 入れ子にすると深い木で(デ)シリアライザのスタックが溢れるので、
+// 木は行きがけ順に並べた平らな列として書き出す
+// 各要素は {"data": 要素, "left": 左の子があるか, "right": 右の子があるか}
+
+#[derive(Serialize)]
+struct RecordRef<'r, T> {
+    data: &'r T,
+    left: bool,
+    right: bool,
+}
+
+#[derive(Deserialize)]
+struct Record<T> {
+    data: T,
+    left: bool,
+    right: bool,
+}
+
+impl<T: Serialize> Serialize for Node<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.len()))?;
+        let mut stack = vec![self];
+
+        while let Some(node) = stack.pop() {
+            seq.serialize_element(&RecordRef {
+                data: &node.data,
+                left: node.left.is_some(),
+                right: node.right.is_some(),
+            })?;
+            stack.extend(node.right());
+            stack.extend(node.left());
+        }
+
+        seq.end()
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Node<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let records: Vec<Record<T>> = Vec::deserialize(deserializer)?;
+        if records.is_empty() {
+            return Err( D::Error::custom("tree must have at least one node") );
+        }
+
+        // まだ埋まっていない子の位置(親の位置, 左右)
+        let mut slots: Vec<(usize, Side)> = Vec::new();
+        let mut children: Children = vec![(None, None); records.len()];
+
+        for (i, record) in records.iter().enumerate() {
+            if i > 0 {
+                match slots.pop() {
+                    Some((parent, Side::Left)) => children[parent].0 = Some(i),
+                    Some((parent, Side::Right)) => children[parent].1 = Some(i),
+                    None => return Err( D::Error::custom(format!("node {} has no parent", i)) ),
+                }
+            }
+            if record.right {
+                slots.push((i, Side::Right));
+            }
+            if record.left {
+                slots.push((i, Side::Left));
+            }
+        }
+
+        if !slots.is_empty() {
+            return Err( D::Error::custom(format!("{} child nodes are missing", slots.len())) );
+        }
+
+        let len = records.len();
+        let data = records.into_iter().map(|r| r.data).collect();
+        Ok(assemble(data, &children, (0..len).rev()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::{NodeError, SearchOrder};
+    use crate::tree::test_util::create_test_tree;
+
+    #[test]
+    fn round_trip() {
+        let mut root = create_test_tree();
+        root.left_mut().unwrap().take_right();
+
+        let json = serde_json::to_string(&root).unwrap();
+        assert!(json.starts_with(r#"[{"data":1,"left":true,"right":true},{"data":2,"left":true,"right":false}"#));
+
+        let back: Node<i32> = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.to_sexpr(), "(1 (2 4 _) (3 6 7))");
+
+        let json = serde_json::to_string(&SearchOrder::LevelOrder).unwrap();
+        assert_eq!(serde_json::from_str::<SearchOrder>(&json).unwrap(), SearchOrder::LevelOrder);
+        let json = serde_json::to_string(&NodeError::ShapeError).unwrap();
+        assert_eq!(serde_json::from_str::<NodeError>(&json).unwrap(), NodeError::ShapeError);
+    }
+
+    #[test]
+    fn invalid_input() {
+        let err = |s: &str| serde_json::from_str::<Node<i32>>(s).unwrap_err().to_string();
+
+        assert!(err("[]").contains("at least one node"));
+        assert!(err(r#"[{"data":1,"left":true,"right":false}]"#).contains("1 child nodes are missing"));
+        assert!(err(r#"[{"data":1,"left":false,"right":false},{"data":2,"left":false,"right":false}]"#).contains("node 1 has no parent"));
+    }
+
+    #[test]
+    fn deep_tree() {
+        let depth = 100_000;
+        let mut root = Node::new(0);
+        for i in 1..depth {
+            let mut parent = Node::new(i);
+            parent.add_node_left(root).unwrap();
+            root = parent;
+        }
+
+        let json = serde_json::to_vec(&root).unwrap();
+        let back: Node<i32> = serde_json::from_slice(&json).unwrap();
+        assert_eq!(back.height(), depth as usize);
+        assert!(back.iter(&SearchOrder::PreOrder).eq(root.iter(&SearchOrder::PreOrder)));
+    }
+}
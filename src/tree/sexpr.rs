@@ -0,0 +1,306 @@
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use super::Node;
+
+// 木の文字列表現
+//   葉        … 要素をそのまま書く                 4
+//   子がある  … (要素 左の子 右の子)               (2 4 5)
+//   空の子    … _                                  (2 _ 5)
+// 空白・括弧・"を含む要素や、空文字列・"_"そのものは"..."で囲む(中の"と\は\でエスケープ)
+// 例: (1 (2 4 5) (3 6 7))
+
+const EMPTY: &str = "_";
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum SexprErrorKind {
+    UnexpectedEnd,          // 途中で文字列が終わった
+    Expected(&'static str), // ここには別のものが来るはず
+    EmptyRoot,              // 根が空(_)になっている
+    UnclosedQuote,          // "が閉じていない
+    Payload(String),        // 要素をFromStrで読めなかった　中身はFromStrのエラー
+    TrailingInput,          // 木を読み終えた後に余計な文字がある
+}
+
+// posは入力の先頭からのバイト位置
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SexprError {
+    pub pos: usize,
+    pub kind: SexprErrorKind,
+}
+
+impl Display for SexprError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.kind {
+            SexprErrorKind::UnexpectedEnd => write!(f, "unexpected end of input at {}", self.pos),
+            SexprErrorKind::Expected(what) => write!(f, "expected {} at {}", what, self.pos),
+            SexprErrorKind::EmptyRoot => write!(f, "root must not be empty at {}", self.pos),
+            SexprErrorKind::UnclosedQuote => write!(f, "unclosed quote at {}", self.pos),
+            SexprErrorKind::Payload(msg) => write!(f, "invalid payload at {}: {}", self.pos, msg),
+            SexprErrorKind::TrailingInput => write!(f, "unexpected input after tree at {}", self.pos),
+        }
+    }
+}
+
+impl<T: Display> Node<T> {
+    pub fn to_sexpr(&self) -> String {
+        enum Emit<'r, T> {
+            Child(Option<&'r Node<T>>),
+            Text(&'static str),
+        }
+
+        let mut out = String::new();
+        let mut stack = vec![Emit::Child(Some(self))];
+
+        while let Some(emit) = stack.pop() {
+            match emit {
+                Emit::Text(s) => out.push_str(s),
+                Emit::Child(None) => out.push_str(EMPTY),
+                Emit::Child(Some(node)) if node.is_leaf() => write_atom(&mut out, &node.data),
+                Emit::Child(Some(node)) => {
+                    out.push('(');
+                    write_atom(&mut out, &node.data);
+                    out.push(' ');
+
+                    stack.push(Emit::Text(")"));
+                    stack.push(Emit::Child(node.right()));
+                    stack.push(Emit::Text(" "));
+                    stack.push(Emit::Child(node.left()));
+                }
+            }
+        }
+
+        out
+    }
+}
+
+fn write_atom<T: Display>(out: &mut String, data: &T) {
+    let s = data.to_string();
+    let quote = s.is_empty() || s == EMPTY || s.chars().any(|c| c.is_whitespace() || c == '(' || c == ')' || c == '"');
+
+    if !quote {
+        out.push_str(&s);
+        return;
+    }
+
+    out.push('"');
+    for c in s.chars() {
+        if c == '"' || c == '\\' {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out.push('"');
+}
+
+impl<T> Node<T>
+where T: FromStr, T::Err: Display {
+    pub fn from_sexpr(s: &str) -> Result<Node<T>, SexprError> {
+        // 組み立て途中の(要素, 読み終えた子)
+        let mut stack: Vec<(T, Vec<Option<Node<T>>>)> = Vec::new();
+        let mut lexer = Lexer { src: s, pos: 0 };
+
+        let root = loop {
+            let (pos, token) = lexer.next()?;
+
+            // 子を2つ読み終えたノードの後には ) しか来ない
+            if let Some((_, children)) = stack.last() {
+                if children.len() == 2 && token != Token::Close {
+                    return Err( SexprError { pos, kind: SexprErrorKind::Expected("')'") } );
+                }
+            }
+
+            let tree = match token {
+                Token::End => return Err( SexprError { pos, kind: SexprErrorKind::UnexpectedEnd } ),
+                Token::Open => {
+                    let (pos, token) = lexer.next()?;
+                    match token {
+                        Token::Atom(atom) => stack.push((parse_payload(&atom, pos)?, Vec::new())),
+                        Token::End => return Err( SexprError { pos, kind: SexprErrorKind::UnexpectedEnd } ),
+                        _ => return Err( SexprError { pos, kind: SexprErrorKind::Expected("payload") } ),
+                    }
+                    continue;
+                },
+                Token::Close => {
+                    match stack.pop() {
+                        Some((data, mut children)) if children.len() == 2 => {
+                            let right = children.pop().unwrap();
+                            let left = children.pop().unwrap();
+                            Some(Node {
+                                data,
+                                left: left.map(Box::new).into(),
+                                right: right.map(Box::new).into(),
+                            })
+                        },
+                        _ => return Err( SexprError { pos, kind: SexprErrorKind::Expected("tree or '_'") } ),
+                    }
+                },
+                Token::Empty => {
+                    if stack.is_empty() {
+                        return Err( SexprError { pos, kind: SexprErrorKind::EmptyRoot } );
+                    }
+                    None
+                },
+                Token::Atom(atom) => Some(Node::new(parse_payload(&atom, pos)?)),
+            };
+
+            match stack.last_mut() {
+                Some((_, children)) => children.push(tree),
+                None => break tree.unwrap(),
+            }
+        };
+
+        match lexer.next()? {
+            (_, Token::End) => Ok(root),
+            (pos, _) => Err( SexprError { pos, kind: SexprErrorKind::TrailingInput } ),
+        }
+    }
+}
+
+fn parse_payload<T>(atom: &str, pos: usize) -> Result<T, SexprError>
+where T: FromStr, T::Err: Display {
+    atom.parse().map_err(|e: T::Err| SexprError { pos, kind: SexprErrorKind::Payload(e.to_string()) })
+}
+
+#[derive(Debug, Eq, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    Empty,
+    Atom(String),
+    End,
+}
+
+struct Lexer<'r> {
+    src: &'r str,
+    pos: usize,
+}
+
+impl<'r> Lexer<'r> {
+    // (トークンの開始位置, トークン)
+    fn next(&mut self) -> Result<(usize, Token), SexprError> {
+        let rest = &self.src[self.pos..];
+        let skipped = rest.len() - rest.trim_start().len();
+        self.pos += skipped;
+
+        let start = self.pos;
+        let mut chars = self.src[start..].chars();
+
+        let token = match chars.next() {
+            None => Token::End,
+            Some('(') => { self.pos += 1; Token::Open },
+            Some(')') => { self.pos += 1; Token::Close },
+            Some('"') => {
+                let mut atom = String::new();
+                let mut len = 1;
+                let mut escaped = false;
+                loop {
+                    let c = match chars.next() {
+                        Some(c) => c,
+                        None => return Err( SexprError { pos: start, kind: SexprErrorKind::UnclosedQuote } ),
+                    };
+                    len += c.len_utf8();
+                    if escaped {
+                        atom.push(c);
+                        escaped = false;
+                    } else if c == '\\' {
+                        escaped = true;
+                    } else if c == '"' {
+                        break;
+                    } else {
+                        atom.push(c);
+                    }
+                }
+                self.pos += len;
+                Token::Atom(atom)
+            },
+            Some(_) => {
+                let len = self.src[start..]
+                    .find(|c: char| c.is_whitespace() || c == '(' || c == ')' || c == '"')
+                    .unwrap_or(self.src.len() - start);
+                self.pos += len;
+
+                let atom = &self.src[start..start + len];
+                if atom == EMPTY { Token::Empty } else { Token::Atom(atom.to_string()) }
+            },
+        };
+
+        Ok((start, token))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::SearchOrder;
+    use crate::tree::test_util::create_test_tree;
+
+    fn collect<T: Clone>(root: &Node<T>, order: SearchOrder) -> Vec<T> {
+        root.iter(&order).cloned().collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        let root = create_test_tree();
+        assert_eq!(root.to_sexpr(), "(1 (2 4 5) (3 6 7))");
+
+        let parsed: Node<i32> = Node::from_sexpr("(1 (2 4 5) (3 6 7))").unwrap();
+        assert_eq!(collect(&parsed, SearchOrder::PreOrder), collect(&root, SearchOrder::PreOrder));
+        assert_eq!(collect(&parsed, SearchOrder::InOrder), collect(&root, SearchOrder::InOrder));
+
+        let parsed: Node<i32> = Node::from_sexpr("\n  (1\n    (2 _ 5)\n    3)\n").unwrap();
+        assert_eq!(parsed.to_sexpr(), "(1 (2 _ 5) 3)");
+        assert!(parsed.left().unwrap().left().is_none());
+
+        let leaf: Node<i32> = Node::from_sexpr("42").unwrap();
+        assert_eq!(leaf.to_sexpr(), "42");
+    }
+
+    #[test]
+    fn quoted_payload() {
+        let mut root = Node::new("a b".to_string());
+        root.create_left_node("_".to_string()).unwrap();
+        root.create_right_node("say \"hi\"".to_string()).unwrap();
+
+        let s = root.to_sexpr();
+        assert_eq!(s, r#"("a b" "_" "say \"hi\"")"#);
+
+        let parsed: Node<String> = Node::from_sexpr(&s).unwrap();
+        assert_eq!(collect(&parsed, SearchOrder::PreOrder), vec!["a b", "_", "say \"hi\""]);
+        assert_eq!(Node::<String>::from_sexpr(r#""""#).unwrap().as_ref(), "");
+    }
+
+    #[test]
+    fn parse_errors() {
+        let err = |s: &str| Node::<i32>::from_sexpr(s).unwrap_err();
+
+        assert_eq!(err(""), SexprError { pos: 0, kind: SexprErrorKind::UnexpectedEnd });
+        assert_eq!(err("(1 2"), SexprError { pos: 4, kind: SexprErrorKind::UnexpectedEnd });
+        assert_eq!(err("_"), SexprError { pos: 0, kind: SexprErrorKind::EmptyRoot });
+        assert_eq!(err("(1 2 3 4)"), SexprError { pos: 7, kind: SexprErrorKind::Expected("')'") });
+        assert_eq!(err("(1 2)"), SexprError { pos: 4, kind: SexprErrorKind::Expected("tree or '_'") });
+        assert_eq!(err("((1 2 3) 4 5)"), SexprError { pos: 1, kind: SexprErrorKind::Expected("payload") });
+        assert_eq!(err("(1 2 3) 4"), SexprError { pos: 8, kind: SexprErrorKind::TrailingInput });
+        assert_eq!(err("(1 \"2 3)"), SexprError { pos: 3, kind: SexprErrorKind::UnclosedQuote });
+
+        let e = err("(1 x 3)");
+        assert_eq!(e.pos, 3);
+        assert_eq!(e.to_string(), "invalid payload at 3: invalid digit found in string");
+    }
+
+    #[test]
+    fn deep_tree() {
+        let depth = 100_000;
+        let mut root = Node::new(0);
+        for i in 1..depth {
+            let mut parent = Node::new(i);
+            parent.add_node_left(root).unwrap();
+            root = parent;
+        }
+
+        let s = root.to_sexpr();
+        let parsed: Node<i32> = Node::from_sexpr(&s).unwrap();
+        assert_eq!(parsed.height(), depth as usize);
+        assert_eq!(parsed.to_sexpr(), s);
+    }
+}
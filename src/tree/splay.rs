@@ -0,0 +1,406 @@
+use std::cmp::Ordering;
+use std::mem;
+
+use super::Node;
+use super::ordered::{self, Entry, InvariantError, Link, MapIter, OrderedMap};
+
+type SplayLink<K, V> = Link<K, V, ()>;
+type SplayNode<K, V> = Node<Entry<K, V>>;
+
+// スプレー木　触ったノードを回転で根まで持ち上げるので、最近使ったキーほど根の近くにある
+// 1回の操作は最悪O(n)だが、ならせばO(log n)になる
+// 木が一直線に伸びることがあるので、スプレー操作は再帰を使わずに上から下へ行う
+#[derive(Debug)]
+pub struct SplayTree<K, V> {
+    root: SplayLink<K, V>,
+    len: usize,
+}
+
+impl<K: Ord, V> SplayTree<K, V> {
+    pub fn new() -> Self {
+        SplayTree {
+            root: None,
+            len: 0,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.root = None;
+        self.len = 0;
+    }
+
+    // 既にキーがある場合は値を置き換え、古い値を返す　どちらの場合もkeyが根になる
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        Self::splay(&mut self.root, |k| key.cmp(k));
+
+        let mut root = match self.root.take() {
+            Some(r) => r,
+            None => {
+                self.root = Some(Box::new(Node::new(Entry::new(key, value, ()))));
+                self.len = 1;
+                return None;
+            }
+        };
+
+        // 根がkeyに最も近いノードになっているので、その左右どちらかを新しいノードに付け替える
+        let mut node = Box::new(Node::new(Entry::new(key, value, ())));
+        match node.data.key.cmp(&root.data.key) {
+            Ordering::Equal => {
+                let old = mem::replace(&mut root.data.value, node.into_data().value);
+                self.root = Some(root);
+                return Some(old);
+            }
+            Ordering::Less => {
+                *node.left = root.left.take();
+                ordered::update_size(&mut root);
+                *node.right = Some(root);
+            }
+            Ordering::Greater => {
+                *node.right = root.right.take();
+                ordered::update_size(&mut root);
+                *node.left = Some(root);
+            }
+        }
+        ordered::update_size(&mut node);
+
+        self.root = Some(node);
+        self.len += 1;
+        None
+    }
+
+    // 見つかったキーを根に持ち上げる　見つからなくても最後に触ったノードが根になる
+    // 形を変えずに探すにはOrderedMap::getを使う
+    pub fn access(&mut self, key: &K) -> Option<&V> {
+        self.get_mut(key).map(|v| &*v)
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        Self::splay(&mut self.root, |k| key.cmp(k));
+
+        match self.root.as_mut() {
+            Some(r) if r.data.key == *key => Some(&mut r.data.value),
+            _ => None,
+        }
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        Self::splay(&mut self.root, |k| key.cmp(k));
+
+        match self.root.as_ref() {
+            Some(r) if r.data.key == *key => {}
+            _ => return None,
+        }
+
+        let mut root = self.root.take().unwrap();
+        self.root = Self::join(root.left.take(), root.right.take());
+        self.len -= 1;
+        Some(root.into_data().value)
+    }
+
+    // key以上のキーをすべて切り離して返す　selfにはkeyより小さいキーが残る
+    pub fn split_off(&mut self, key: &K) -> Self {
+        Self::splay(&mut self.root, |k| key.cmp(k));
+
+        let mut root = match self.root.take() {
+            Some(r) => r,
+            None => return Self::new(),
+        };
+
+        let right = if root.data.key >= *key {
+            self.root = root.left.take();
+            ordered::update_size(&mut root);
+            Some(root)
+        } else {
+            let right = root.right.take();
+            ordered::update_size(&mut root);
+            self.root = Some(root);
+            right
+        };
+
+        self.len = ordered::size_of(self.root());
+        let len = ordered::size_of(right.as_deref());
+        SplayTree {
+            root: right,
+            len,
+        }
+    }
+
+    // otherの要素をすべて移す　キーの範囲が重ならなければ根でつなぐだけで済む
+    // 重なる場合は小さい方の木の要素を1つずつ挿入する(同じキーはotherの値になる)
+    pub fn merge(&mut self, mut other: Self) {
+        let (self_min, self_max) = match (self.min(), self.max()) {
+            (Some(a), Some(b)) => (a.0, b.0),
+            _ => {
+                mem::swap(self, &mut other);
+                return;
+            }
+        };
+        let (other_min, other_max) = match (other.min(), other.max()) {
+            (Some(a), Some(b)) => (a.0, b.0),
+            _ => return,
+        };
+
+        if self_max < other_min {
+            self.root = Self::join(self.root.take(), other.root.take());
+        } else if other_max < self_min {
+            self.root = Self::join(other.root.take(), self.root.take());
+        } else if self.len >= other.len {
+            for (k, v) in ordered::into_entries(other.root.take()) {
+                self.insert(k, v);
+            }
+            return;
+        } else {
+            for (k, v) in ordered::into_entries(self.root.take()) {
+                if !other.contains_key(&k) {
+                    other.insert(k, v);
+                }
+            }
+            *self = other;
+            return;
+        }
+        self.len += other.len;
+    }
+
+    pub fn check_invariants(&self) -> Result<(), InvariantError> {
+        ordered::check_order(self.root(), self.len)?;
+        ordered::check_size(self.root())
+    }
+
+    // leftのキーがすべてrightのキーより小さいときに1つの木にする
+    fn join(left: SplayLink<K, V>, right: SplayLink<K, V>) -> SplayLink<K, V> {
+        let mut left = match left {
+            Some(l) => Some(l),
+            None => return right,
+        };
+
+        // 最大のノードを根に持ち上げると右の子が空くので、そこにrightをつなぐ
+        Self::splay(&mut left, |_| Ordering::Greater);
+        let root = left.as_mut().unwrap();
+        *root.right = right;
+        ordered::update_size(root);
+        left
+    }
+
+    // 上から下へのスプレー操作　cmpは目標とノードのキーの比較結果を返す
+    // 目標より小さいノードはlessに、大きいノードはgreaterに順に外していき、最後に根の左右へ組み立て直す
+    fn splay<F>(link: &mut SplayLink<K, V>, mut cmp: F)
+    where F: FnMut(&K) -> Ordering {
+        let mut t = match link.take() {
+            Some(t) => t,
+            None => return,
+        };
+        let mut less: Vec<Box<SplayNode<K, V>>> = Vec::new();      // 右の子をつないでいく
+        let mut greater: Vec<Box<SplayNode<K, V>>> = Vec::new();   // 左の子をつないでいく
+
+        loop {
+            match cmp(&t.data.key) {
+                Ordering::Less => {
+                    match t.left.as_ref().map(|n| cmp(&n.data.key)) {
+                        None => break,
+                        Some(Ordering::Less) => {
+                            // 同じ向きに2段降りる場合は先に回転しておく(zig-zig)
+                            t.rotate_right().unwrap();
+                            ordered::update_size(t.right_mut().unwrap());
+                            if t.left.is_none() {
+                                break;
+                            }
+                        }
+                        Some(_) => {}
+                    }
+                    let next = t.left.take().unwrap();
+                    greater.push(mem::replace(&mut t, next));
+                }
+                Ordering::Greater => {
+                    match t.right.as_ref().map(|n| cmp(&n.data.key)) {
+                        None => break,
+                        Some(Ordering::Greater) => {
+                            t.rotate_left().unwrap();
+                            ordered::update_size(t.left_mut().unwrap());
+                            if t.right.is_none() {
+                                break;
+                            }
+                        }
+                        Some(_) => {}
+                    }
+                    let next = t.right.take().unwrap();
+                    less.push(mem::replace(&mut t, next));
+                }
+                Ordering::Equal => break,
+            }
+        }
+
+        // 外したノードは下に付けたものから順に要素数を計算し直す
+        let mut acc = t.left.take();
+        while let Some(mut n) = less.pop() {
+            *n.right = acc;
+            ordered::update_size(&mut n);
+            acc = Some(n);
+        }
+        *t.left = acc;
+
+        let mut acc = t.right.take();
+        while let Some(mut n) = greater.pop() {
+            *n.left = acc;
+            ordered::update_size(&mut n);
+            acc = Some(n);
+        }
+        *t.right = acc;
+
+        ordered::update_size(&mut t);
+        *link = Some(t);
+    }
+}
+
+impl<K: Ord, V> Default for SplayTree<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord, V> OrderedMap<K, V> for SplayTree<K, V> {
+    type Meta = ();
+
+    fn root(&self) -> Option<&SplayNode<K, V>> {
+        self.root.as_deref()
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+ordered::impl_map_into_iter!(SplayTree, ());
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::test_util;
+
+    fn root_key(tree: &SplayTree<i32, i32>) -> Option<i32> {
+        tree.root().map(|n| n.data.key)
+    }
+
+    #[test]
+    fn access_moves_to_root() {
+        let mut tree = SplayTree::new();
+        for k in 0..20 {
+            tree.insert(k, k * 10);
+            assert_eq!(root_key(&tree), Some(k));
+        }
+
+        assert_eq!(tree.access(&7), Some(&70));
+        assert_eq!(root_key(&tree), Some(7));
+
+        // getでは形が変わらない
+        assert_eq!(tree.get(&15), Some(&150));
+        assert_eq!(root_key(&tree), Some(7));
+
+        // 見つからないときは最後に触ったノードが根になる
+        assert_eq!(tree.access(&100), None);
+        assert_eq!(root_key(&tree), Some(19));
+
+        *tree.get_mut(&3).unwrap() += 1;
+        assert_eq!(tree.get(&3), Some(&31));
+        assert_eq!(tree.insert(3, 0), Some(31));
+        assert_eq!(tree.len(), 20);
+        tree.check_invariants().unwrap();
+    }
+
+    #[test]
+    fn remove() {
+        let mut tree = SplayTree::new();
+        for k in 0..50 {
+            tree.insert(k, k);
+        }
+        for k in (0..50).filter(|k| k % 3 != 1) {
+            assert_eq!(tree.remove(&k), Some(k));
+            tree.check_invariants().unwrap();
+        }
+        assert_eq!(tree.remove(&0), None);
+        assert_eq!(tree.len(), 17);
+
+        let keys: Vec<i32> = tree.iter().map(|(k, _)| *k).collect();
+        assert_eq!(keys, (0..50).filter(|k| k % 3 == 1).collect::<Vec<i32>>());
+        assert_eq!(tree.select(4).map(|(k, _)| *k), Some(13));
+        assert_eq!(tree.rank(&13), 4);
+    }
+
+    #[test]
+    fn skewed_access() {
+        let mut tree = SplayTree::new();
+        for k in 0..1000 {
+            tree.insert(k, k);
+        }
+
+        // 同じキーばかり引くと根の近くに集まる
+        for _ in 0..10 {
+            for k in 500..504 {
+                assert_eq!(tree.access(&k), Some(&k));
+            }
+        }
+        let depth = tree.root().unwrap().iter_with_depth()
+            .find(|(_, e)| e.key == 502)
+            .map(|(d, _)| d)
+            .unwrap();
+        assert!(depth <= 3);
+        tree.check_invariants().unwrap();
+    }
+
+    #[test]
+    fn split_and_merge() {
+        let mut tree = SplayTree::new();
+        for k in 0..100 {
+            tree.insert(k, k);
+        }
+
+        // 30以上70未満をまとめて取り除く
+        let mut upper = tree.split_off(&30);
+        let rest = upper.split_off(&70);
+        assert_eq!(tree.len(), 30);
+        assert_eq!(upper.len(), 40);
+        assert_eq!(upper.min(), Some((&30, &30)));
+        assert_eq!(upper.max(), Some((&69, &69)));
+        tree.merge(rest);
+        tree.check_invariants().unwrap();
+        upper.check_invariants().unwrap();
+
+        assert_eq!(tree.len(), 60);
+        assert_eq!(tree.rank(&70), 30);
+        assert!(tree.range(25..75).map(|(k, _)| *k).eq((25..30).chain(70..75)));
+
+        // 範囲が重なる場合は値をotherの方で上書きする
+        let mut other = SplayTree::new();
+        for k in (0..100).step_by(10) {
+            other.insert(k, -k);
+        }
+        tree.merge(other);
+        tree.check_invariants().unwrap();
+        assert_eq!(tree.len(), 64);
+        assert_eq!(tree.get(&40), Some(&-40));
+        assert_eq!(tree.get(&20), Some(&-20));
+
+        assert_eq!(tree.split_off(&1000).len(), 0);
+        assert_eq!(tree.split_off(&0).len(), 64);
+        assert!(tree.is_empty());
+    }
+
+    #[test]
+    fn deep_tree() {
+        // 昇順に入れると一直線の木になるが、スプレー操作は再帰しないのでスタックは溢れない
+        let mut tree = SplayTree::new();
+        for k in 0..100000 {
+            tree.insert(k, ());
+        }
+        assert_eq!(tree.root().unwrap().height(), 100000);
+
+        assert_eq!(tree.access(&0), Some(&()));
+        assert!(tree.root().unwrap().height() < 100000);
+        assert_eq!(tree.remove(&1), Some(()));
+        tree.check_invariants().unwrap();
+    }
+
+    #[test]
+    fn compare_with_btreemap() {
+        test_util::compare_with_btreemap(&mut SplayTree::new(), 13);
+    }
+}
@@ -0,0 +1,96 @@
+// 各モジュールのテストで共通に使う木や乱数、順序付きの木の検査
+use std::collections::BTreeMap;
+
+use super::{AvlTree, BstMap, InvariantError, Node, OrderedMap, RbTree, SplayTree, Treap};
+
+//        1
+//      /   \
+//     2     3
+//    / \   / \
+//   4   5 6   7
+pub(crate) fn create_test_tree() -> Node<i32> {
+    let mut root = Node::new(1);
+    let mut left = Node::new(2);
+    let mut right = Node::new(3);
+
+    left.add_node_left(Node::new(4)).unwrap();
+    left.add_node_right(Node::new(5)).unwrap();
+
+    right.add_node_left(Node::new(6)).unwrap();
+    right.add_node_right(Node::new(7)).unwrap();
+
+    root.add_node_left(left).unwrap();
+    root.add_node_right(right).unwrap();
+
+    root
+}
+
+// 簡単な線形合同法　テストの再現性のために固定の種を使う
+pub(crate) fn lcg(seed: &mut u64) -> u64 {
+    *seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+    *seed >> 33
+}
+
+// 順序付きの木の、種類ごとに形の違う更新操作をまとめる
+// 引くときはget_mutを使う　スプレー木ではこれで触ったノードが根に上がる
+pub(crate) trait TestMap<K: Ord, V>: OrderedMap<K, V> {
+    fn insert(&mut self, key: K, value: V) -> Option<V>;
+    fn remove(&mut self, key: &K) -> Option<V>;
+    fn get_mut(&mut self, key: &K) -> Option<&mut V>;
+    fn check_invariants(&self) -> Result<(), InvariantError>;
+}
+
+macro_rules! impl_test_map {
+    ($($map:ident),*) => {
+        $(
+            impl<K: Ord, V> TestMap<K, V> for $map<K, V> {
+                fn insert(&mut self, key: K, value: V) -> Option<V> {
+                    $map::insert(self, key, value)
+                }
+
+                fn remove(&mut self, key: &K) -> Option<V> {
+                    $map::remove(self, key)
+                }
+
+                fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+                    $map::get_mut(self, key)
+                }
+
+                fn check_invariants(&self) -> Result<(), InvariantError> {
+                    $map::check_invariants(self)
+                }
+            }
+        )*
+    };
+}
+
+impl_test_map!(BstMap, AvlTree, RbTree, SplayTree, Treap);
+
+// BTreeMapと同じ操作を乱数で3000回行い、毎回結果と不変条件を確かめる
+// 最後に並び、範囲、順位も比べる
+pub(crate) fn compare_with_btreemap<M: TestMap<u64, usize>>(map: &mut M, mut seed: u64) {
+    let mut expected = BTreeMap::new();
+
+    for i in 0..3000 {
+        let key = lcg(&mut seed) % 400;
+
+        match lcg(&mut seed) % 3 {
+            0 => assert_eq!(map.remove(&key), expected.remove(&key)),
+            1 => assert_eq!(map.get_mut(&key), expected.get_mut(&key)),
+            _ => assert_eq!(map.insert(key, i), expected.insert(key, i)),
+        }
+        assert_eq!(map.len(), expected.len());
+        map.check_invariants().unwrap();
+    }
+
+    assert!(map.iter().eq(expected.iter()));
+    assert!(map.range(100..200).eq(expected.range(100..200)));
+    assert!(map.range(100..=300).eq(expected.range(100..=300)));
+    assert!(map.range(..250).eq(expected.range(..250)));
+
+    for (k, e) in expected.iter().enumerate() {
+        assert_eq!(map.select(k), Some(e));
+        assert_eq!(map.rank(e.0), k);
+    }
+    assert_eq!(map.select(expected.len()), None);
+}
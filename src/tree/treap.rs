@@ -0,0 +1,352 @@
+use std::cmp::Ordering;
+use std::mem;
+
+use super::Node;
+use super::ordered::{self, Entry, InvariantError, Link, MapIter, OrderedMap};
+
+type TreapLink<K, V> = Link<K, V, u64>;
+type TreapNode<K, V> = Node<Entry<K, V, u64>>;
+
+// 優先度を作る擬似乱数(SplitMix64)　種が同じなら同じ操作列から同じ形の木ができる
+#[derive(Debug, Clone)]
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+}
+
+const DEFAULT_SEED: u64 = 0x2545f4914f6cdd1d;
+
+// トリープ　キーについては二分探索木、乱数の優先度については親が子以上になるヒープにする
+// 優先度がランダムなので、挿入の順序によらず高さの期待値がO(log n)になる
+// Entryのmetaには優先度を入れる
+#[derive(Debug)]
+pub struct Treap<K, V> {
+    root: TreapLink<K, V>,
+    len: usize,
+    rng: SplitMix64,
+}
+
+impl<K: Ord, V> Treap<K, V> {
+    pub fn new() -> Self {
+        Self::with_seed(DEFAULT_SEED)
+    }
+
+    pub fn with_seed(seed: u64) -> Self {
+        Treap {
+            root: None,
+            len: 0,
+            rng: SplitMix64(seed),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.root = None;
+        self.len = 0;
+    }
+
+    // 既にキーがある場合は値を置き換え、古い値を返す
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        if let Some(node) = ordered::find_link(&mut self.root, &key) {
+            return Some(mem::replace(&mut node.data.value, value));
+        }
+
+        // 新しいノードより優先度の高いノードの間は普通に降り、
+        // 低いノードに当たったらその部分木をkeyで分けて新しいノードの左右にする
+        let priority = self.rng.next();
+        let mut link = &mut self.root;
+        while let Some(go_left) = link.as_ref().filter(|n| n.data.meta >= priority).map(|n| key < n.data.key) {
+            let node = link.as_mut().unwrap();
+            node.data.size += 1;
+            link = if go_left { &mut node.left } else { &mut node.right };
+        }
+
+        let (left, right) = Self::split(link.take(), &key);
+        let mut node = Box::new(Node::new(Entry::new(key, value, priority)));
+        *node.left = left;
+        *node.right = right;
+        ordered::update_size(&mut node);
+        *link = Some(node);
+
+        self.len += 1;
+        None
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        ordered::get_mut(&mut self.root, key)
+    }
+
+    // 取り除くノードの左右の部分木をmergeしてその位置に置く
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        if !self.contains_key(key) {
+            return None;
+        }
+
+        let mut link = &mut self.root;
+        while let Some(ord) = link.as_ref().map(|n| key.cmp(&n.data.key)) {
+            if ord == Ordering::Equal {
+                break;
+            }
+            let node = link.as_mut().unwrap();
+            node.data.size -= 1;
+            link = if ord == Ordering::Less { &mut node.left } else { &mut node.right };
+        }
+
+        let mut node = link.take().unwrap();
+        *link = Self::join(node.left.take(), node.right.take());
+        self.len -= 1;
+        Some(node.into_data().value)
+    }
+
+    // key以上のキーをすべて切り離して返す　selfにはkeyより小さいキーが残る
+    // 切り離した木の乱数の種はselfの乱数から作る
+    pub fn split_off(&mut self, key: &K) -> Self {
+        let (left, right) = Self::split(self.root.take(), key);
+        self.root = left;
+        self.len = ordered::size_of(self.root());
+
+        Treap {
+            len: ordered::size_of(right.as_deref()),
+            root: right,
+            rng: SplitMix64(self.rng.next()),
+        }
+    }
+
+    // otherの要素をすべて移す　キーの範囲が重ならなければ優先度に従ってつなぐだけで済む
+    // 重なる場合は小さい方の木の要素を1つずつ挿入する(同じキーはotherの値になる)
+    pub fn merge(&mut self, mut other: Self) {
+        let (self_min, self_max) = match (self.min(), self.max()) {
+            (Some(a), Some(b)) => (a.0, b.0),
+            _ => {
+                self.root = other.root.take();
+                self.len = other.len;
+                return;
+            }
+        };
+        let (other_min, other_max) = match (other.min(), other.max()) {
+            (Some(a), Some(b)) => (a.0, b.0),
+            _ => return,
+        };
+
+        if self_max < other_min {
+            self.root = Self::join(self.root.take(), other.root.take());
+        } else if other_max < self_min {
+            self.root = Self::join(other.root.take(), self.root.take());
+        } else if self.len >= other.len {
+            for (k, v) in ordered::into_entries(other.root.take()) {
+                self.insert(k, v);
+            }
+            return;
+        } else {
+            mem::swap(&mut self.root, &mut other.root);
+            mem::swap(&mut self.len, &mut other.len);
+            for (k, v) in ordered::into_entries(other.root.take()) {
+                if !self.contains_key(&k) {
+                    self.insert(k, v);
+                }
+            }
+            return;
+        }
+        self.len += other.len;
+    }
+
+    // キーの順序、要素数、優先度のヒープ条件、部分木の要素数を確かめる
+    pub fn check_invariants(&self) -> Result<(), InvariantError> {
+        ordered::check_order(self.root(), self.len)?;
+
+        if let Some(root) = self.root() {
+            root.try_fold(|e, left: Option<u64>, right: Option<u64>| {
+                if left.into_iter().chain(right).any(|p| p > e.meta) {
+                    Err( InvariantError::Priority )
+                } else {
+                    Ok(e.meta)
+                }
+            })?;
+        }
+        ordered::check_size(self.root())
+    }
+
+    // keyより小さいキーの木とkey以上のキーの木に分ける
+    fn split(link: TreapLink<K, V>, key: &K) -> (TreapLink<K, V>, TreapLink<K, V>) {
+        let mut node = match link {
+            Some(n) => n,
+            None => return (None, None),
+        };
+
+        if node.data.key < *key {
+            let (left, right) = Self::split(node.right.take(), key);
+            *node.right = left;
+            ordered::update_size(&mut node);
+            (Some(node), right)
+        } else {
+            let (left, right) = Self::split(node.left.take(), key);
+            *node.left = right;
+            ordered::update_size(&mut node);
+            (left, Some(node))
+        }
+    }
+
+    // leftのキーがすべてrightのキーより小さいときに、優先度の高い方を根にしてつなぐ
+    fn join(left: TreapLink<K, V>, right: TreapLink<K, V>) -> TreapLink<K, V> {
+        match (left, right) {
+            (None, r) => r,
+            (l, None) => l,
+            (Some(mut l), Some(mut r)) => {
+                if l.data.meta >= r.data.meta {
+                    *l.right = Self::join(l.right.take(), Some(r));
+                    ordered::update_size(&mut l);
+                    Some(l)
+                } else {
+                    *r.left = Self::join(Some(l), r.left.take());
+                    ordered::update_size(&mut r);
+                    Some(r)
+                }
+            }
+        }
+    }
+}
+
+impl<K: Ord, V> Default for Treap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord, V> OrderedMap<K, V> for Treap<K, V> {
+    type Meta = u64;
+
+    fn root(&self) -> Option<&TreapNode<K, V>> {
+        self.root.as_deref()
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+ordered::impl_map_into_iter!(Treap, u64);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::SearchOrder;
+    use crate::tree::test_util;
+
+    fn preorder_keys(tree: &Treap<i32, ()>) -> Vec<i32> {
+        tree.root().map_or(Vec::new(), |r| r.iter(&SearchOrder::PreOrder).map(|e| e.key).collect())
+    }
+
+    #[test]
+    fn insert_get_remove() {
+        let mut tree = Treap::new();
+        for k in 0..50 {
+            assert_eq!(tree.insert(k, k * 2), None);
+        }
+        assert_eq!(tree.insert(10, 0), Some(20));
+        *tree.get_mut(&11).unwrap() += 1;
+        assert_eq!(tree.get(&11), Some(&23));
+        assert_eq!(tree.get(&50), None);
+        tree.check_invariants().unwrap();
+
+        for k in (0..50).filter(|k| k % 3 != 1) {
+            assert!(tree.remove(&k).is_some());
+            tree.check_invariants().unwrap();
+        }
+        assert_eq!(tree.remove(&0), None);
+        assert_eq!(tree.len(), 17);
+        assert_eq!(tree.min(), Some((&1, &2)));
+        assert_eq!(tree.median(), Some((&25, &50)));
+    }
+
+    #[test]
+    fn seed_is_deterministic() {
+        let build = |seed| {
+            let mut tree = Treap::with_seed(seed);
+            for k in 0..200 {
+                tree.insert(k, ());
+            }
+            tree
+        };
+
+        assert_eq!(preorder_keys(&build(1)), preorder_keys(&build(1)));
+        assert_ne!(preorder_keys(&build(1)), preorder_keys(&build(2)));
+        assert_eq!(preorder_keys(&Treap::new()), Vec::<i32>::new());
+    }
+
+    #[test]
+    fn sorted_insert_stays_shallow() {
+        let mut tree = Treap::new();
+        for k in 0..100000 {
+            tree.insert(k, ());
+        }
+        tree.check_invariants().unwrap();
+
+        // 期待値は2 ln n 程度(約23)なので、十分な余裕を見ておく
+        assert!(tree.root().unwrap().height() < 60);
+    }
+
+    #[test]
+    fn split_and_merge() {
+        let mut tree = Treap::with_seed(5);
+        for k in 0..100 {
+            tree.insert(k, k);
+        }
+
+        // 30以上70未満をまとめて取り除く
+        let mut upper = tree.split_off(&30);
+        let rest = upper.split_off(&70);
+        assert_eq!(tree.len(), 30);
+        assert_eq!(upper.len(), 40);
+        assert_eq!(rest.len(), 30);
+        tree.merge(rest);
+        tree.check_invariants().unwrap();
+        upper.check_invariants().unwrap();
+
+        assert_eq!(tree.len(), 60);
+        assert_eq!(tree.select(30), Some((&70, &70)));
+        assert!(upper.iter().map(|(k, _)| *k).eq(30..70));
+
+        // 範囲が重なる場合は値をotherの方で上書きする
+        let mut small = Treap::with_seed(6);
+        for k in (0..100).step_by(10) {
+            small.insert(k, -k);
+        }
+        small.merge(tree);
+        small.check_invariants().unwrap();
+        assert_eq!(small.len(), 64);
+        assert_eq!(small.get(&40), Some(&-40));
+        assert_eq!(small.get(&20), Some(&20));
+
+        // 空の木とのmerge
+        let mut empty = Treap::new();
+        empty.merge(small);
+        assert_eq!(empty.len(), 64);
+        empty.merge(Treap::new());
+        assert_eq!(empty.len(), 64);
+        empty.check_invariants().unwrap();
+    }
+
+    #[test]
+    fn check_invariants_detects_errors() {
+        let mut tree = Treap::new();
+        for k in 0..10 {
+            tree.insert(k, ());
+        }
+        tree.check_invariants().unwrap();
+
+        // 根の優先度を最低にすると子の方が高くなる
+        tree.root.as_mut().unwrap().data.meta = 0;
+        assert_eq!(tree.check_invariants(), Err(InvariantError::Priority));
+    }
+
+    #[test]
+    fn compare_with_btreemap() {
+        test_util::compare_with_btreemap(&mut Treap::with_seed(17), 17);
+    }
+}
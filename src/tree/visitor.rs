@@ -0,0 +1,248 @@
+use super::Node;
+
+// Visitorの各メソッドが返す、この後の進め方
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum VisitControl {
+    Continue,       // そのまま続ける
+    SkipChildren,   // このノードの子には降りない(enter_nodeで返したときだけ意味がある)
+    Stop,           // ここで巡回をやめる
+}
+
+// walkで木を巡回するときに呼ばれるフック　使わないメソッドは実装しなくてよい
+// 子を持つノードではenter_node → 子 → leave_node の順に、葉ではvisit_leafだけが呼ばれる
+// depthは根を0とした深さ
+pub trait Visitor<T> {
+    fn enter_node(&mut self, _data: &T, _depth: usize) -> VisitControl {
+        VisitControl::Continue
+    }
+
+    fn leave_node(&mut self, _data: &T, _depth: usize) -> VisitControl {
+        VisitControl::Continue
+    }
+
+    fn visit_leaf(&mut self, _data: &T, _depth: usize) -> VisitControl {
+        VisitControl::Continue
+    }
+}
+
+// Visitorの値を書き換えられる版　木の形は変えられない
+pub trait VisitorMut<T> {
+    fn enter_node(&mut self, _data: &mut T, _depth: usize) -> VisitControl {
+        VisitControl::Continue
+    }
+
+    fn leave_node(&mut self, _data: &mut T, _depth: usize) -> VisitControl {
+        VisitControl::Continue
+    }
+
+    fn visit_leaf(&mut self, _data: &mut T, _depth: usize) -> VisitControl {
+        VisitControl::Continue
+    }
+}
+
+enum Step<N, D> {
+    Enter(N, usize),
+    Leave(D, usize),
+}
+
+// 行きがけに左の子から順にvisitorを呼ぶ　深い木でもスタックが溢れないように自前のスタックで回す
+// SkipChildrenで子を飛ばしたノードでもleave_nodeは呼ぶ
+// 最後まで回ったらtrue、Stopで止まったらfalseを返す
+pub fn walk<T, V: Visitor<T> + ?Sized>(root: &Node<T>, visitor: &mut V) -> bool {
+    let mut stack = vec![Step::Enter(root, 0)];
+
+    while let Some(step) = stack.pop() {
+        let control = match step {
+            Step::Enter(node, depth) if node.is_leaf() => visitor.visit_leaf(&node.data, depth),
+            Step::Enter(node, depth) => {
+                let control = visitor.enter_node(&node.data, depth);
+                stack.push(Step::Leave(&node.data, depth));
+                if control == VisitControl::Continue {
+                    stack.extend(node.right().map(|n| Step::Enter(n, depth + 1)));
+                    stack.extend(node.left().map(|n| Step::Enter(n, depth + 1)));
+                }
+                control
+            }
+            Step::Leave(data, depth) => visitor.leave_node(data, depth),
+        };
+
+        if control == VisitControl::Stop {
+            return false;
+        }
+    }
+    true
+}
+
+// walkの値を書き換えられる版
+pub fn walk_mut<T, V: VisitorMut<T> + ?Sized>(root: &mut Node<T>, visitor: &mut V) -> bool {
+    let mut stack = vec![Step::Enter(root, 0)];
+
+    while let Some(step) = stack.pop() {
+        let control = match step {
+            Step::Enter(node, depth) if node.is_leaf() => visitor.visit_leaf(&mut node.data, depth),
+            Step::Enter(node, depth) => {
+                // データと左右の子を別々に借りて、子を回った後にデータに戻ってこられるようにする
+                let Node { data, left, right } = node;
+                let control = visitor.enter_node(data, depth);
+                stack.push(Step::Leave(data, depth));
+                if control == VisitControl::Continue {
+                    stack.extend(right.as_deref_mut().map(|n| Step::Enter(n, depth + 1)));
+                    stack.extend(left.as_deref_mut().map(|n| Step::Enter(n, depth + 1)));
+                }
+                control
+            }
+            Step::Leave(data, depth) => visitor.leave_node(data, depth),
+        };
+
+        if control == VisitControl::Stop {
+            return false;
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //       +
+    //      / \
+    //     *   -
+    //    / \   \
+    //   1   2   3
+    fn create_expr_tree() -> Node<&'static str> {
+        let mut root = Node::new("+");
+        let mul = root.create_left_node("*").unwrap();
+        mul.create_left_node("1").unwrap();
+        mul.create_right_node("2").unwrap();
+        root.create_right_node("-").unwrap().create_right_node("3").unwrap();
+        root
+    }
+
+    // 呼ばれた順に記録する
+    #[derive(Default)]
+    struct Recorder {
+        log: Vec<String>,
+        skip: Option<&'static str>,
+        stop: Option<&'static str>,
+    }
+
+    impl Recorder {
+        fn control(&self, data: &str) -> VisitControl {
+            if self.stop == Some(data) {
+                VisitControl::Stop
+            } else if self.skip == Some(data) {
+                VisitControl::SkipChildren
+            } else {
+                VisitControl::Continue
+            }
+        }
+    }
+
+    impl Visitor<&'static str> for Recorder {
+        fn enter_node(&mut self, data: &&'static str, depth: usize) -> VisitControl {
+            self.log.push(format!("enter {} {}", data, depth));
+            self.control(data)
+        }
+
+        fn leave_node(&mut self, data: &&'static str, _depth: usize) -> VisitControl {
+            self.log.push(format!("leave {}", data));
+            VisitControl::Continue
+        }
+
+        fn visit_leaf(&mut self, data: &&'static str, depth: usize) -> VisitControl {
+            self.log.push(format!("leaf {} {}", data, depth));
+            self.control(data)
+        }
+    }
+
+    #[test]
+    fn walk_order() {
+        let tree = create_expr_tree();
+        let mut rec = Recorder::default();
+
+        assert!(walk(&tree, &mut rec));
+        assert_eq!(rec.log, vec![
+            "enter + 0", "enter * 1", "leaf 1 2", "leaf 2 2", "leave *",
+            "enter - 1", "leaf 3 2", "leave -", "leave +",
+        ]);
+    }
+
+    #[test]
+    fn skip_and_stop() {
+        let tree = create_expr_tree();
+
+        let mut rec = Recorder { skip: Some("*"), ..Default::default() };
+        assert!(walk(&tree, &mut rec));
+        assert_eq!(rec.log, vec!["enter + 0", "enter * 1", "leave *", "enter - 1", "leaf 3 2", "leave -", "leave +"]);
+
+        let mut rec = Recorder { stop: Some("2"), ..Default::default() };
+        assert!(!walk(&tree, &mut rec));
+        assert_eq!(rec.log, vec!["enter + 0", "enter * 1", "leaf 1 2", "leaf 2 2"]);
+
+        // 根だけの木は葉として扱う
+        let mut rec = Recorder::default();
+        assert!(walk(&Node::new("x"), &mut rec));
+        assert_eq!(rec.log, vec!["leaf x 0"]);
+    }
+
+    // 深さ1までのノードに100を足し、深さ1のノードの子には降りない　103になった葉で止める
+    struct AddShallow;
+
+    impl VisitorMut<i32> for AddShallow {
+        fn enter_node(&mut self, data: &mut i32, depth: usize) -> VisitControl {
+            *data += 100;
+            if depth < 1 { VisitControl::Continue } else { VisitControl::SkipChildren }
+        }
+
+        fn visit_leaf(&mut self, data: &mut i32, _depth: usize) -> VisitControl {
+            *data += 100;
+            if *data == 103 { VisitControl::Stop } else { VisitControl::Continue }
+        }
+    }
+
+    #[test]
+    fn walk_mut_updates() {
+        //     0
+        //    / \
+        //   1   2
+        //  /   / \
+        // 4   3   5
+        let mut tree = Node::new(0);
+        tree.create_left_node(1).unwrap().create_left_node(4).unwrap();
+        let right = tree.create_right_node(2).unwrap();
+        right.create_left_node(3).unwrap();
+        right.create_right_node(5).unwrap();
+
+        assert!(walk_mut(&mut tree, &mut AddShallow));
+        assert_eq!(tree.to_level_order(), vec![Some(100), Some(101), Some(102), Some(4), None, Some(3), Some(5)]);
+
+        // 葉に直接当てればStopで止まる
+        let mut leaf = Node::new(3);
+        assert!(!walk_mut(&mut leaf, &mut AddShallow));
+        assert_eq!(*leaf.as_ref(), 103);
+    }
+
+    // 最大の深さを数える
+    struct MaxDepth(usize);
+
+    impl Visitor<u32> for MaxDepth {
+        fn visit_leaf(&mut self, _data: &u32, depth: usize) -> VisitControl {
+            self.0 = self.0.max(depth);
+            VisitControl::Continue
+        }
+    }
+
+    #[test]
+    fn deep_tree() {
+        let mut tree = Node::new(0u32);
+        let mut cur = &mut tree;
+        for i in 1..100000 {
+            cur = cur.create_right_node(i).unwrap();
+        }
+
+        let mut depth = MaxDepth(0);
+        assert!(walk(&tree, &mut depth));
+        assert_eq!(depth.0, 99999);
+    }
+}
@@ -3,6 +3,7 @@ use std::fmt;
 use std::collections::HashMap;
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct VarData(pub String, pub f64);
 
 impl fmt::Display for VarData {
@@ -12,6 +13,7 @@ impl fmt::Display for VarData {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct VarPool {
     pool: HashMap<String, f64>,
     size: usize,
@@ -52,4 +54,19 @@ mod tests {
         assert_eq!( p.get(&"y".to_string()), None );
     }
 
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        let mut p = VarPool::new();
+        p.insert(VarData("x".to_string(), 1.5));
+
+        let json = serde_json::to_string(&p).unwrap();
+        let q: VarPool = serde_json::from_str(&json).unwrap();
+        assert_eq!( q.get(&"ans".to_string()), Some(0.0) );
+        assert_eq!( q.get(&"x".to_string()), Some(1.5) );
+
+        let d: VarData = serde_json::from_str(r#"["y",2.0]"#).unwrap();
+        assert_eq!( d, VarData("y".to_string(), 2.0) );
+    }
+
 }